@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE clicks (
+    id bigserial PRIMARY KEY,
+    url_id uuid REFERENCES urls(id) ON DELETE CASCADE NOT NULL,
+    referrer varchar(2048),
+    user_agent varchar(512),
+    ip_hash varchar(64),
+    accept_language varchar(256),
+    created_at timestamp DEFAULT now() NOT NULL
+);
+
+CREATE INDEX clicks_url_id_created_at_idx ON clicks (url_id, created_at);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP SEQUENCE short_url_seq;
\ No newline at end of file
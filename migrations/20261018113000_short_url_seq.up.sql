@@ -0,0 +1,2 @@
+-- Add up migration script here
+CREATE SEQUENCE short_url_seq;
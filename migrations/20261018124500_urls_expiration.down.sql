@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE urls
+    DROP COLUMN expires_at,
+    DROP COLUMN max_visits,
+    DROP COLUMN fallback_url,
+    DROP COLUMN active;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE urls
+    ADD COLUMN expires_at timestamp,
+    ADD COLUMN max_visits integer CHECK (max_visits > 0),
+    ADD COLUMN fallback_url varchar(2048),
+    ADD COLUMN active boolean DEFAULT true NOT NULL;
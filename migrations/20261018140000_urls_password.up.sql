@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE urls ADD COLUMN password varchar(256);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE urls DROP COLUMN preview;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP INDEX urls_creator_created_at_id_idx;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add up migration script here
+CREATE INDEX urls_creator_created_at_id_idx ON urls (creator, created_at, id);
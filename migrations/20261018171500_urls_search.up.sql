@@ -0,0 +1,18 @@
+-- Add up migration script here
+ALTER TABLE urls ADD COLUMN search tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A')
+    || setweight(to_tsvector('english', description), 'B')
+    || setweight(
+        to_tsvector(
+            'english',
+            translate(
+                regexp_replace(long_url, '^[^:]+://([^/?#]*)([^?#]*).*$', '\1 \2'),
+                '/.-_',
+                '    '
+            )
+        ),
+        'C'
+    )
+) STORED;
+
+CREATE INDEX urls_search_idx ON urls USING GIN (search);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP FUNCTION url_tag_names;
+DROP TABLE url_tags;
+DROP TABLE tags;
\ No newline at end of file
@@ -0,0 +1,23 @@
+-- Add up migration script here
+CREATE TABLE tags (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner uuid REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    name varchar(32) NOT NULL CHECK (name ~ '^[a-z0-9\-_]{1,32}$'),
+    created_at timestamp DEFAULT now() NOT NULL,
+    UNIQUE (owner, name)
+);
+
+CREATE TABLE url_tags (
+    url_id uuid REFERENCES urls(id) ON DELETE CASCADE NOT NULL,
+    tag_id uuid REFERENCES tags(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (url_id, tag_id)
+);
+
+CREATE INDEX url_tags_tag_id_idx ON url_tags (tag_id);
+
+CREATE FUNCTION url_tag_names(url_id uuid) RETURNS varchar[] AS $$
+    SELECT COALESCE(array_agg(tags.name ORDER BY tags.name), '{}')
+    FROM url_tags
+    JOIN tags ON tags.id = url_tags.tag_id
+    WHERE url_tags.url_id = $1;
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE url_revisions (
+    id bigserial PRIMARY KEY,
+    url_id uuid REFERENCES urls(id) ON DELETE CASCADE NOT NULL,
+    editor uuid REFERENCES users(id) NOT NULL,
+    old_long_url varchar(2048),
+    new_long_url varchar(2048) NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL
+);
+
+CREATE INDEX url_revisions_url_id_created_at_idx ON url_revisions (url_id, created_at);
+
+INSERT INTO url_revisions (url_id, editor, old_long_url, new_long_url, created_at)
+SELECT id, creator, NULL, long_url, created_at
+FROM urls;
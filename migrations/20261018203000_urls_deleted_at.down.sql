@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE urls DROP COLUMN deleted_at;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE urls ADD COLUMN deleted_at timestamp;
+
+CREATE INDEX urls_deleted_at_idx ON urls (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE users DROP COLUMN private_profile;
+
+ALTER TABLE urls DROP COLUMN visibility;
+
+DROP TYPE url_visibility;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE TYPE url_visibility AS ENUM ('private', 'unlisted', 'public');
+
+ALTER TABLE urls ADD COLUMN visibility url_visibility DEFAULT 'private' NOT NULL;
+
+ALTER TABLE users ADD COLUMN private_profile boolean DEFAULT false NOT NULL;
@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE urls DROP COLUMN workspace_id;
+
+DROP TABLE workspace_members;
+
+DROP TABLE workspaces;
+
+DROP TYPE workspace_role;
\ No newline at end of file
@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TYPE workspace_role AS ENUM ('viewer', 'editor', 'admin', 'owner');
+
+CREATE TABLE workspaces (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name varchar(64) NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL
+);
+
+CREATE TABLE workspace_members (
+    workspace_id uuid REFERENCES workspaces(id) ON DELETE CASCADE NOT NULL,
+    user_id uuid REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    role workspace_role NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user_id_idx ON workspace_members (user_id);
+
+ALTER TABLE urls ADD COLUMN workspace_id uuid REFERENCES workspaces(id) ON DELETE SET NULL;
+
+CREATE INDEX urls_workspace_id_created_at_id_idx ON urls (workspace_id, created_at, id);
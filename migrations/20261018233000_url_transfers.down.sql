@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE url_transfers;
+
+DROP TYPE transfer_status;
\ No newline at end of file
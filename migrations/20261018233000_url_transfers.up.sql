@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TYPE transfer_status AS ENUM ('pending', 'accepted', 'declined', 'cancelled');
+
+CREATE TABLE url_transfers (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    url_id uuid REFERENCES urls(id) ON DELETE CASCADE NOT NULL,
+    from_user uuid REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    to_user uuid REFERENCES users(id) ON DELETE CASCADE,
+    to_workspace uuid REFERENCES workspaces(id) ON DELETE CASCADE,
+    status transfer_status DEFAULT 'pending' NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL,
+    resolved_at timestamp,
+    CHECK ((to_user IS NULL) <> (to_workspace IS NULL))
+);
+
+CREATE UNIQUE INDEX url_transfers_pending_url_id_idx ON url_transfers (url_id)
+WHERE status = 'pending';
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE clicks
+DROP COLUMN country,
+DROP COLUMN browser,
+DROP COLUMN os;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE clicks
+ADD COLUMN country char(2),
+ADD COLUMN browser varchar(64),
+ADD COLUMN os varchar(64);
+
+UPDATE clicks
+SET country = upper(substring(accept_language FROM '^[A-Za-z]{2,3}[-_]([A-Za-z]{2})\y'));
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE webhook_deliveries;
+
+DROP TABLE webhooks;
+
+DROP TYPE delivery_status;
\ No newline at end of file
@@ -0,0 +1,36 @@
+-- Add up migration script here
+CREATE TYPE delivery_status AS ENUM ('pending', 'delivered', 'failed');
+
+CREATE TABLE webhooks (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner uuid REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    url varchar(2048) NOT NULL,
+    secret char(64) NOT NULL,
+    events varchar(32)[] NOT NULL,
+    active boolean DEFAULT true NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL,
+    updated_at timestamp DEFAULT now() NOT NULL
+);
+
+CREATE INDEX webhooks_owner_idx ON webhooks (owner);
+
+CREATE TABLE webhook_deliveries (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    webhook_id uuid REFERENCES webhooks(id) ON DELETE CASCADE NOT NULL,
+    redelivery_of uuid REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    event varchar(32) NOT NULL,
+    payload text NOT NULL,
+    status delivery_status DEFAULT 'pending' NOT NULL,
+    attempts integer DEFAULT 0 NOT NULL,
+    response_status integer,
+    error varchar(256),
+    created_at timestamp DEFAULT now() NOT NULL,
+    last_attempt_at timestamp,
+    next_attempt_at timestamp DEFAULT now(),
+    delivered_at timestamp
+);
+
+CREATE INDEX webhook_deliveries_webhook_id_created_at_idx ON webhook_deliveries (webhook_id, created_at);
+
+CREATE INDEX webhook_deliveries_pending_idx ON webhook_deliveries (next_attempt_at)
+WHERE status = 'pending';
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE api_tokens (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    name varchar(64) NOT NULL,
+    token_hash char(64) UNIQUE NOT NULL,
+    hint char(8) NOT NULL,
+    scopes varchar(32)[] NOT NULL,
+    created_at timestamp DEFAULT now() NOT NULL,
+    expires_at timestamp,
+    last_used_at timestamp
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens (user_id);
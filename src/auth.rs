@@ -1,17 +1,28 @@
-use crate::{config::Config, Validate};
+use crate::{config::Config, db::Db, error::ErrorBody, Validate};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 use rocket::{
-    http::Status,
+    fairing::AdHoc,
+    http::{Header as HttpHeader, Status},
+    outcome::try_outcome,
     request::{FromRequest, Outcome},
-    serde::{uuid::Uuid, Deserialize, Serialize},
-    Request,
+    serde::{json::Json, uuid::Uuid, Deserialize, Serialize},
+    Request, Responder,
 };
-use rocket_db_pools::sqlx;
+use rocket_db_pools::{sqlx, Database};
+use scopes::RequiredScope;
+use sha2::{Digest, Sha256};
+use sqlx::types::chrono::NaiveDateTime;
+use std::{marker::PhantomData, ops::Deref};
 
 pub mod handlers;
+pub mod passwords;
 mod repo;
+pub mod scopes;
 mod validators;
 
+/// Prefix of personal access tokens, telling them apart from JWTs.
+const API_TOKEN_PREFIX: &str = "urlessen_";
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 #[serde(rename_all = "camelCase")]
@@ -50,6 +61,7 @@ pub struct User {
     username: String,
     password: String,
     created_at: sqlx::types::chrono::NaiveDateTime,
+    private_profile: bool,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -75,38 +87,295 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
     type Error = ();
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let auth_header = req.headers().get_one("Authorization");
-        let config = req.rocket().state::<Config>().unwrap();
-
-        match auth_header {
-            None => Outcome::Forward(Status::Unauthorized),
-            Some(h) => {
-                let parts = h.splitn(2, ' ').collect::<Vec<_>>();
-
-                if parts.len() != 2 || parts[0].to_uppercase() != "BEARER" {
-                    return Outcome::Forward(Status::Unauthorized);
-                }
-
-                let token = parts[1];
-                let decode_result = jsonwebtoken::decode::<Claims>(
-                    token,
-                    &DecodingKey::from_secret(config.access_token_secret.as_bytes()),
-                    &Validation::new(jsonwebtoken::Algorithm::HS256),
-                );
-
-                match decode_result {
-                    Ok(payload) => Outcome::Success(payload.claims.user),
-                    Err(_) => Outcome::Forward(Status::Unauthorized),
-                }
+        authenticate(req).await.map(|(user, _)| user)
+    }
+}
+
+/// An authenticated user whose credentials grant the scope `S`, e.g.
+/// `Scoped<UrlsWrite>`. Lacking it fails the request with `403 Forbidden`,
+/// which [`challenge`] turns into a challenge for the missing scope.
+pub struct Scoped<S: RequiredScope> {
+    user: AuthenticatedUser,
+    scopes: Vec<Scope>,
+    scope: PhantomData<S>,
+}
+
+impl<S: RequiredScope> Scoped<S> {
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+}
+
+impl<S: RequiredScope> Deref for Scoped<S> {
+    type Target = AuthenticatedUser;
+
+    fn deref(&self) -> &Self::Target {
+        &self.user
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, S: RequiredScope> FromRequest<'r> for Scoped<S> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let (user, scopes) = try_outcome!(authenticate(req).await);
+
+        if !scopes.contains(&S::SCOPE) {
+            req.local_cache(|| MissingScope(Some(S::SCOPE)));
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+
+        Outcome::Success(Scoped {
+            user,
+            scopes,
+            scope: PhantomData,
+        })
+    }
+}
+
+/// Scope a request was denied for, if any.
+struct MissingScope(Option<Scope>);
+
+/// Adds the `WWW-Authenticate` challenge of RFC 6750 to the responses of
+/// requests rejected by a [`Scoped`] guard.
+pub fn challenge() -> AdHoc {
+    AdHoc::on_response("Insufficient Scope", |req, res| {
+        Box::pin(async move {
+            if res.status() != Status::Forbidden {
+                return;
+            }
+
+            if let MissingScope(Some(scope)) = req.local_cache(|| MissingScope(None)) {
+                res.set_header(scope_challenge(*scope));
+            }
+        })
+    })
+}
+
+/// Rejects a request for a scope that only some of its parameters require,
+/// with the same challenge a [`Scoped`] guard would get.
+#[derive(Responder)]
+#[response(status = 403)]
+pub struct InsufficientScope(Json<ErrorBody>, HttpHeader<'static>);
+
+impl InsufficientScope {
+    pub fn new(scope: Scope) -> Self {
+        InsufficientScope(
+            Json(ErrorBody {
+                error: "insufficient_scope",
+                message: format!("This request requires the {} scope", scope.as_str()),
+            }),
+            scope_challenge(scope),
+        )
+    }
+}
+
+fn scope_challenge(scope: Scope) -> HttpHeader<'static> {
+    HttpHeader::new(
+        "WWW-Authenticate",
+        format!(
+            "Bearer error=\"insufficient_scope\", scope=\"{}\"",
+            scope.as_str()
+        ),
+    )
+}
+
+/// Identifies the user behind the bearer token of a request, along with the
+/// scopes the token grants.
+async fn authenticate(req: &Request<'_>) -> Outcome<(AuthenticatedUser, Vec<Scope>), ()> {
+    let auth_header = req.headers().get_one("Authorization");
+    let config = req.rocket().state::<Config>().unwrap();
+
+    match auth_header {
+        None => Outcome::Forward(Status::Unauthorized),
+        Some(h) => {
+            let parts = h.splitn(2, ' ').collect::<Vec<_>>();
+
+            if parts.len() != 2 || parts[0].to_uppercase() != "BEARER" {
+                return Outcome::Forward(Status::Unauthorized);
+            }
+
+            let token = parts[1];
+
+            if token.starts_with(API_TOKEN_PREFIX) {
+                return from_api_token(req, token).await;
+            }
+
+            let decode_result = jsonwebtoken::decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(config.access_token_secret.as_bytes()),
+                &Validation::new(jsonwebtoken::Algorithm::HS256),
+            );
+
+            match decode_result {
+                Ok(payload) => Outcome::Success((payload.claims.user, payload.claims.scopes)),
+                Err(_) => Outcome::Forward(Status::Unauthorized),
             }
         }
     }
 }
 
+async fn from_api_token(
+    req: &Request<'_>,
+    token: &str,
+) -> Outcome<(AuthenticatedUser, Vec<Scope>), ()> {
+    let pool = Db::fetch(req.rocket()).unwrap();
+    let result = match pool.acquire().await {
+        Ok(mut db) => repo::use_api_token(&mut db, &hash_api_token(token)).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some((user, scopes))) => Outcome::Success((
+            user,
+            scopes.iter().filter_map(|s| Scope::parse(s)).collect(),
+        )),
+        Ok(None) => Outcome::Forward(Status::Unauthorized),
+        Err(_) => Outcome::Error((Status::InternalServerError, ())),
+    }
+}
+
+/// Personal access tokens are random enough for a plain digest to be safe to
+/// store, and looking them up by it avoids a slow password hash per request.
+fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: Uuid,
+    pub username: String,
+    pub created_at: sqlx::types::chrono::NaiveDateTime,
+    pub private_profile: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileBody {
+    private_profile: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+pub enum Scope {
+    #[serde(rename = "urls:read")]
+    UrlsRead,
+    #[serde(rename = "urls:write")]
+    UrlsWrite,
+    #[serde(rename = "stats:read")]
+    StatsRead,
+    #[serde(rename = "profile:read")]
+    ProfileRead,
+    #[serde(rename = "profile:write")]
+    ProfileWrite,
+    #[serde(rename = "tokens:read")]
+    TokensRead,
+    #[serde(rename = "tokens:write")]
+    TokensWrite,
+    #[serde(rename = "workspaces:read")]
+    WorkspacesRead,
+    #[serde(rename = "workspaces:write")]
+    WorkspacesWrite,
+    #[serde(rename = "webhooks:read")]
+    WebhooksRead,
+    #[serde(rename = "webhooks:write")]
+    WebhooksWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 11] = [
+        Scope::UrlsRead,
+        Scope::UrlsWrite,
+        Scope::StatsRead,
+        Scope::ProfileRead,
+        Scope::ProfileWrite,
+        Scope::TokensRead,
+        Scope::TokensWrite,
+        Scope::WorkspacesRead,
+        Scope::WorkspacesWrite,
+        Scope::WebhooksRead,
+        Scope::WebhooksWrite,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::UrlsRead => "urls:read",
+            Scope::UrlsWrite => "urls:write",
+            Scope::StatsRead => "stats:read",
+            Scope::ProfileRead => "profile:read",
+            Scope::ProfileWrite => "profile:write",
+            Scope::TokensRead => "tokens:read",
+            Scope::TokensWrite => "tokens:write",
+            Scope::WorkspacesRead => "workspaces:read",
+            Scope::WorkspacesWrite => "workspaces:write",
+            Scope::WebhooksRead => "webhooks:read",
+            Scope::WebhooksWrite => "webhooks:write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        Scope::ALL.into_iter().find(|s| s.as_str() == scope)
+    }
+
+    /// Scopes of the tokens issued on sign in, which grant everything.
+    fn all() -> Vec<Self> {
+        Scope::ALL.to_vec()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    id: Uuid,
+    name: String,
+    hint: String,
+    scopes: Vec<String>,
+    created_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    last_used_at: Option<NaiveDateTime>,
+}
+
+/// A newly created token, which is the only time its value is shown.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewApiToken {
+    token: String,
+
+    #[serde(flatten)]
+    api_token: ApiToken,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBody {
+    name: String,
+    scopes: Vec<Scope>,
+    expires_at: Option<NaiveDateTime>,
+}
+
+impl Validate for TokenBody {
+    fn validate(&self) -> bool {
+        validators::is_valid_token_name(&self.name)
+            && !self.scopes.is_empty()
+            && self
+                .expires_at
+                .is_none_or(validators::is_valid_token_expiration)
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Claims {
     user: AuthenticatedUser,
+    // Tokens issued before scopes existed grant everything, as they did then
+    #[serde(default = "Scope::all")]
+    scopes: Vec<Scope>,
     exp: usize,
 }
 
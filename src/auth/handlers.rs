@@ -1,21 +1,22 @@
-use super::{repo, AuthenticatedUser, Claims, SignInResponse, Validate};
+use super::{
+    hash_api_token, passwords, repo,
+    scopes::{ProfileRead, ProfileWrite, TokensRead, TokensWrite},
+    ApiToken, AuthenticatedUser, Claims, NewApiToken, Profile, ProfileBody, Scope, Scoped,
+    SignInResponse, TokenBody, Validate, API_TOKEN_PREFIX,
+};
 use crate::{
     auth::{SignIn, SignUp},
     config::Config,
     db::Db,
+    utils::compute_random_32_bytes_key,
 };
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-use rand::rngs::OsRng;
 use rocket::{
     http::{Cookie, CookieJar, SameSite, Status},
     serde::json::Json,
     State,
 };
 use rocket_db_pools::Connection;
-use std::sync::Arc;
+use sqlx::types::Uuid;
 
 #[rocket::post("/signup", data = "<body>")]
 pub async fn signup(
@@ -27,30 +28,9 @@ pub async fn signup(
         return Err(Status::UnprocessableEntity);
     }
 
-    let body = Arc::new(body);
-    let body_clone = body.clone();
-    let argon_secret_clone = config.argon_secret.clone();
-
-    let password_hash = rocket::tokio::task::spawn_blocking(move || {
-        let argon = Argon2::new_with_secret(
-            argon_secret_clone.as_bytes(),
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::default(),
-        )
-        .or(Err(Status::InternalServerError))?;
-
-        let salt = SaltString::generate(&mut OsRng);
-
-        match argon.hash_password(body_clone.password.as_bytes(), &salt) {
-            Err(_) => Err(Status::InternalServerError),
-            Ok(h) => Ok(h.to_string()),
-        }
-    })
-    .await
-    .or(Err(Status::InternalServerError))??;
+    let password_hash = passwords::hash_password(&config.argon_secret, &body.password).await?;
 
-    let user = repo::insert_user(&mut db, &body.clone().username, &password_hash)
+    let user = repo::insert_user(&mut db, &body.username, &password_hash)
         .await
         .map_err(|e| match e.as_database_error() {
             Some(e) if e.is_unique_violation() => Status::Conflict,
@@ -71,32 +51,12 @@ pub async fn signin(
         return Err(Status::UnprocessableEntity);
     }
 
-    let body = Arc::new(body);
-    let body_clone = body.clone();
-    let argon_secret_clone = config.argon_secret.clone();
-
-    let user = Arc::new(
-        repo::get_user_by_username(&mut db, &body_clone.username)
-            .await
-            .or(Err(Status::InternalServerError))?
-            .ok_or(Status::Unauthorized)?,
-    );
+    let user = repo::get_user_by_username(&mut db, &body.username)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::Unauthorized)?;
 
-    let user_clone = user.clone();
-
-    rocket::tokio::task::spawn_blocking(move || {
-        let argon = Argon2::new_with_secret(
-            argon_secret_clone.as_bytes(),
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::default(),
-        )?;
-        let password_hash = PasswordHash::new(&user_clone.password)?;
-        argon.verify_password(body_clone.password.as_bytes(), &password_hash)
-    })
-    .await
-    .or(Err(Status::InternalServerError))?
-    .or(Err(Status::Unauthorized))?;
+    passwords::verify_password(&config.argon_secret, &body.password, &user.password).await?;
 
     if let Some(c) = cookies.get_private("session") {
         repo::delete_all_user_sessions_on_reuse(&mut db, user.id, c.value())
@@ -107,6 +67,7 @@ pub async fn signin(
     let now = chrono::Utc::now().timestamp() as usize;
     let mut claims = Claims {
         user: AuthenticatedUser::from_user(&user),
+        scopes: Scope::all(),
         exp: now + config.access_token_ttl_sec as usize,
     };
 
@@ -166,6 +127,7 @@ pub async fn refresh(
     let now = chrono::Utc::now().timestamp() as usize;
     let mut claims = Claims {
         user: user.clone(),
+        scopes: Scope::all(),
         exp: now + config.access_token_ttl_sec as usize,
     };
 
@@ -179,7 +141,7 @@ pub async fn refresh(
         .encode(config.refresh_token_secret.as_bytes())
         .or(Err(Status::InternalServerError))?;
 
-    repo::update_session(&mut db, user_id, &session.unwrap().value(), &refresh_token)
+    repo::update_session(&mut db, user_id, session.unwrap().value(), &refresh_token)
         .await
         .or(Err(Status::InternalServerError))?;
 
@@ -210,7 +172,7 @@ pub async fn logout(
         cookies.remove_private(Cookie::build("session").same_site(SameSite::None));
 
         match result {
-            Err(_) => return Err(Status::InternalServerError),
+            Err(_) => Err(Status::InternalServerError),
             Ok(r) if r.rows_affected() == 0 => {
                 repo::delete_all_user_sessions_on_reuse(&mut db, user_id, c.value())
                     .await
@@ -224,3 +186,97 @@ pub async fn logout(
         Err(Status::Unauthorized)
     }
 }
+
+#[rocket::get("/me")]
+pub async fn get_profile(
+    mut db: Connection<Db>,
+    user: Scoped<ProfileRead>,
+) -> Result<Json<Profile>, Status> {
+    let profile = repo::get_profile(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(profile))
+}
+
+#[rocket::patch("/me", data = "<body>")]
+pub async fn patch_profile(
+    mut db: Connection<Db>,
+    user: Scoped<ProfileWrite>,
+    body: Json<ProfileBody>,
+) -> Result<Json<Profile>, Status> {
+    let profile = repo::update_profile(&mut db, user.id, body.private_profile)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(profile))
+}
+
+#[rocket::get("/tokens")]
+pub async fn get_tokens(
+    mut db: Connection<Db>,
+    user: Scoped<TokensRead>,
+) -> Result<Json<Vec<ApiToken>>, Status> {
+    let tokens = repo::get_api_tokens(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(tokens))
+}
+
+#[rocket::post("/tokens", data = "<body>")]
+pub async fn create_token(
+    mut db: Connection<Db>,
+    user: Scoped<TokensWrite>,
+    body: Json<TokenBody>,
+) -> Result<Json<NewApiToken>, Status> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    // Tokens cannot be used to mint tokens more powerful than themselves
+    if !body.scopes.iter().all(|s| user.scopes().contains(s)) {
+        return Err(Status::Forbidden);
+    }
+
+    let key = compute_random_32_bytes_key();
+    let token = format!("{API_TOKEN_PREFIX}{key}");
+
+    let mut scopes = body
+        .scopes
+        .iter()
+        .map(|s| s.as_str().to_string())
+        .collect::<Vec<_>>();
+    scopes.sort();
+    scopes.dedup();
+
+    let api_token = repo::insert_api_token(
+        &mut db,
+        user.id,
+        body.name.trim(),
+        &hash_api_token(&token),
+        &key[..8],
+        &scopes,
+        body.expires_at,
+    )
+    .await
+    .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(NewApiToken { token, api_token }))
+}
+
+#[rocket::delete("/tokens/<id>")]
+pub async fn revoke_token(
+    mut db: Connection<Db>,
+    user: Scoped<TokensWrite>,
+    id: Uuid,
+) -> Result<Json<ApiToken>, Status> {
+    let api_token = repo::delete_api_token(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(api_token))
+}
@@ -0,0 +1,58 @@
+use argon2::{
+    password_hash::{self, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+};
+use rand::rngs::OsRng;
+use rocket::http::Status;
+
+fn argon(secret: &[u8]) -> Result<Argon2<'_>, argon2::Error> {
+    Argon2::new_with_secret(
+        secret,
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+}
+
+pub async fn hash_password(argon_secret: &str, password: &str) -> Result<String, Status> {
+    let argon_secret = argon_secret.to_owned();
+    let password = password.to_owned();
+
+    rocket::tokio::task::spawn_blocking(move || {
+        let argon = argon(argon_secret.as_bytes()).or(Err(Status::InternalServerError))?;
+        let salt = SaltString::generate(&mut OsRng);
+
+        match argon.hash_password(password.as_bytes(), &salt) {
+            Err(_) => Err(Status::InternalServerError),
+            Ok(h) => Ok(h.to_string()),
+        }
+    })
+    .await
+    .or(Err(Status::InternalServerError))?
+}
+
+pub async fn verify_password(
+    argon_secret: &str,
+    password: &str,
+    password_hash: &str,
+) -> Result<(), Status> {
+    let argon_secret = argon_secret.to_owned();
+    let password = password.to_owned();
+    let password_hash = password_hash.to_owned();
+
+    rocket::tokio::task::spawn_blocking(move || {
+        let argon = argon(argon_secret.as_bytes()).or(Err(Status::InternalServerError))?;
+        let password_hash =
+            PasswordHash::new(&password_hash).or(Err(Status::InternalServerError))?;
+
+        // Only a mismatch is the client's fault, a hash that cannot be read or
+        // checked is ours
+        match argon.verify_password(password.as_bytes(), &password_hash) {
+            Ok(()) => Ok(()),
+            Err(password_hash::Error::Password) => Err(Status::Unauthorized),
+            Err(_) => Err(Status::InternalServerError),
+        }
+    })
+    .await
+    .or(Err(Status::InternalServerError))?
+}
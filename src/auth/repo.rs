@@ -1,5 +1,9 @@
-use super::{AuthenticatedUser, User};
-use sqlx::{postgres::PgQueryResult, types::Uuid, PgConnection};
+use super::{ApiToken, AuthenticatedUser, Profile, User};
+use sqlx::{
+    postgres::PgQueryResult,
+    types::{chrono::NaiveDateTime, Uuid},
+    PgConnection,
+};
 
 pub async fn insert_user(
     db: &mut PgConnection,
@@ -102,3 +106,149 @@ pub async fn delete_session(
     .execute(&mut *db)
     .await
 }
+
+pub async fn get_profile(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Option<Profile>, sqlx::Error> {
+    sqlx::query_as!(
+        Profile,
+        r#"
+        SELECT id, username, created_at, private_profile
+        FROM users
+        WHERE id = $1;
+        "#,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn update_profile(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    private_profile: Option<bool>,
+) -> Result<Option<Profile>, sqlx::Error> {
+    sqlx::query_as!(
+        Profile,
+        r#"
+        UPDATE users SET
+            private_profile = COALESCE($2, private_profile)
+        WHERE id = $1
+        RETURNING id, username, created_at, private_profile;
+        "#,
+        user_id,
+        private_profile,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_api_tokens(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<ApiToken>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiToken,
+        r#"
+        SELECT id, name, hint, scopes, created_at, expires_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at, id;
+        "#,
+        user_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn insert_api_token(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    name: &str,
+    token_hash: &str,
+    hint: &str,
+    scopes: &[String],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<ApiToken, sqlx::Error> {
+    sqlx::query_as!(
+        ApiToken,
+        r#"
+        INSERT INTO api_tokens (user_id, name, token_hash, hint, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, hint, scopes, created_at, expires_at, last_used_at;
+        "#,
+        user_id,
+        name,
+        token_hash,
+        hint,
+        scopes,
+        expires_at,
+    )
+    .fetch_one(&mut *db)
+    .await
+}
+
+pub async fn delete_api_token(
+    db: &mut PgConnection,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<ApiToken>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiToken,
+        r#"
+        DELETE FROM api_tokens
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, name, hint, scopes, created_at, expires_at, last_used_at;
+        "#,
+        id,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+/// Looks up the owner and scopes of an unexpired token, recording that it was
+/// used. The time of use is kept to the minute, so that a busy token does not
+/// update its row on every request.
+pub async fn use_api_token(
+    db: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<(AuthenticatedUser, Vec<String>)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        WITH token AS (
+            SELECT
+                api_tokens.id AS token_id, api_tokens.last_used_at, api_tokens.scopes,
+                users.id, users.username, users.created_at
+            FROM api_tokens
+            JOIN users ON users.id = api_tokens.user_id
+            WHERE
+                api_tokens.token_hash = $1
+                AND (api_tokens.expires_at IS NULL OR api_tokens.expires_at > now())
+        ), used AS (
+            UPDATE api_tokens
+            SET last_used_at = now()
+            FROM token
+            WHERE
+                api_tokens.id = token.token_id
+                AND (token.last_used_at IS NULL OR token.last_used_at < now() - INTERVAL '1 minute')
+        )
+        SELECT id AS "id!", username AS "username!", created_at AS "created_at!", scopes AS "scopes!"
+        FROM token;
+        "#,
+        token_hash,
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+
+    Ok(row.map(|r| {
+        let user = AuthenticatedUser {
+            id: r.id,
+            username: r.username,
+            created_at: r.created_at,
+        };
+
+        (user, r.scopes)
+    }))
+}
@@ -0,0 +1,33 @@
+//! Marker types naming the scope a [`super::Scoped`] guard requires.
+
+use super::Scope;
+
+pub trait RequiredScope: Send + Sync + 'static {
+    const SCOPE: Scope;
+}
+
+macro_rules! required_scopes {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl RequiredScope for $name {
+                const SCOPE: Scope = Scope::$name;
+            }
+        )*
+    };
+}
+
+required_scopes!(
+    UrlsRead,
+    UrlsWrite,
+    StatsRead,
+    ProfileRead,
+    ProfileWrite,
+    TokensRead,
+    TokensWrite,
+    WorkspacesRead,
+    WorkspacesWrite,
+    WebhooksRead,
+    WebhooksWrite,
+);
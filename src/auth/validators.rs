@@ -1,3 +1,5 @@
+use sqlx::types::chrono::NaiveDateTime;
+
 pub fn is_valid_username(username: &str) -> bool {
     username.len() >= 2
         && username.len() <= 32
@@ -13,3 +15,11 @@ pub fn is_valid_password(password: &str) -> bool {
 
     password.len() >= 12 && alphabetic_count > 0 && ascii_digit_count > 0 && other_count > 0
 }
+
+pub fn is_valid_token_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.len() <= 64
+}
+
+pub fn is_valid_token_expiration(expires_at: NaiveDateTime) -> bool {
+    expires_at > chrono::Utc::now().naive_utc()
+}
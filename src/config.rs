@@ -1,24 +1,147 @@
 use crate::utils::compute_random_32_bytes_key;
-use rocket::serde::Deserialize;
+use rocket::{response::Redirect, serde::Deserialize};
 
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Config {
+    pub public_url: String,
     pub argon_secret: String,
     pub access_token_secret: String,
     pub refresh_token_secret: String,
+    pub ip_hash_secret: String,
+    pub country_header: Option<String>,
     pub refresh_token_ttl_sec: u64,
     pub access_token_ttl_sec: u64,
+    pub redirect_status: RedirectStatus,
+    pub short_url_strategy: ShortUrlStrategy,
+    pub short_url_length: usize,
+    pub short_url_alphabet: String,
+    pub short_url_salt: String,
+    pub short_url_max_attempts: usize,
+    pub expiration_interval_sec: u64,
+    pub unlock_ttl_sec: u64,
+    pub trash_retention_sec: u64,
+    pub purge_interval_sec: u64,
+    pub bulk_max_rows: usize,
+    pub bulk_max_bytes: u64,
+    pub live_events_capacity: usize,
+    pub live_replay_size: usize,
+    pub webhook_interval_sec: u64,
+    pub webhook_batch_size: usize,
+    pub webhook_timeout_sec: u64,
+    pub webhook_max_attempts: u32,
+    pub webhook_backoff_sec: u64,
+    pub webhook_retention_sec: u64,
+    pub webhook_prune_interval_sec: u64,
+    pub webhook_allow_private_hosts: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            public_url: String::from("http://localhost:8000"),
             argon_secret: compute_random_32_bytes_key(),
             access_token_secret: compute_random_32_bytes_key(),
             refresh_token_secret: compute_random_32_bytes_key(),
+            ip_hash_secret: compute_random_32_bytes_key(),
+            country_header: None,
             refresh_token_ttl_sec: 172800,
             access_token_ttl_sec: 3600,
+            redirect_status: RedirectStatus::Found,
+            short_url_strategy: ShortUrlStrategy::Random,
+            short_url_length: 8,
+            short_url_alphabet: String::from(
+                "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+            ),
+            short_url_salt: String::new(),
+            short_url_max_attempts: 5,
+            expiration_interval_sec: 60,
+            unlock_ttl_sec: 3600,
+            trash_retention_sec: 2592000,
+            purge_interval_sec: 3600,
+            bulk_max_rows: 1000,
+            bulk_max_bytes: 1048576,
+            live_events_capacity: 1024,
+            live_replay_size: 256,
+            webhook_interval_sec: 5,
+            webhook_batch_size: 50,
+            webhook_timeout_sec: 10,
+            webhook_max_attempts: 8,
+            webhook_backoff_sec: 30,
+            webhook_retention_sec: 604800,
+            webhook_prune_interval_sec: 3600,
+            webhook_allow_private_hosts: false,
+        }
+    }
+}
+
+impl Config {
+    /// Rejects the settings that would otherwise make the background tasks or
+    /// the live events panic or spin once running, such as a zero interval or
+    /// webhook batch size.
+    pub fn check(&self) -> Result<(), String> {
+        let positive = [
+            ("expiration_interval_sec", self.expiration_interval_sec),
+            ("purge_interval_sec", self.purge_interval_sec),
+            ("live_events_capacity", self.live_events_capacity as u64),
+            ("live_replay_size", self.live_replay_size as u64),
+            ("webhook_interval_sec", self.webhook_interval_sec),
+            ("webhook_batch_size", self.webhook_batch_size as u64),
+            ("webhook_timeout_sec", self.webhook_timeout_sec),
+            (
+                "webhook_prune_interval_sec",
+                self.webhook_prune_interval_sec,
+            ),
+        ];
+
+        match positive.iter().find(|(_, value)| *value == 0) {
+            Some((name, _)) => Err(format!("invalid {name} 0, expected a positive value")),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum ShortUrlStrategy {
+    Random,
+    Sequence,
+    Hashid,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(crate = "rocket::serde", try_from = "u16")]
+pub enum RedirectStatus {
+    MovedPermanently,
+    Found,
+    TemporaryRedirect,
+    PermanentRedirect,
+}
+
+impl TryFrom<u16> for RedirectStatus {
+    type Error = String;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        match code {
+            301 => Ok(RedirectStatus::MovedPermanently),
+            302 => Ok(RedirectStatus::Found),
+            307 => Ok(RedirectStatus::TemporaryRedirect),
+            308 => Ok(RedirectStatus::PermanentRedirect),
+            _ => Err(format!(
+                "invalid redirect status {code}, expected one of 301, 302, 307 or 308"
+            )),
+        }
+    }
+}
+
+impl RedirectStatus {
+    pub fn redirect(self, to: String) -> Redirect {
+        match self {
+            RedirectStatus::MovedPermanently => Redirect::moved(to),
+            RedirectStatus::Found => Redirect::found(to),
+            RedirectStatus::TemporaryRedirect => Redirect::temporary(to),
+            RedirectStatus::PermanentRedirect => Redirect::permanent(to),
         }
     }
 }
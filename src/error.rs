@@ -0,0 +1,56 @@
+use rocket::{
+    http::Status,
+    response::status::Custom,
+    serde::{json::Json, Serialize},
+    Responder,
+};
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ErrorBody {
+    pub error: &'static str,
+    pub message: String,
+}
+
+#[derive(Responder)]
+pub enum ApiError {
+    Body(Custom<Json<ErrorBody>>),
+    Status(Status),
+}
+
+impl ApiError {
+    pub fn new(status: Status, error: &'static str, message: impl Into<String>) -> Self {
+        ApiError::Body(Custom(
+            status,
+            Json(ErrorBody {
+                error,
+                message: message.into(),
+            }),
+        ))
+    }
+
+    pub fn into_parts(self) -> (Status, ErrorBody) {
+        match self {
+            ApiError::Body(Custom(status, Json(body))) => (status, body),
+            ApiError::Status(status) => (
+                status,
+                ErrorBody {
+                    error: match status.code {
+                        403 => "forbidden",
+                        404 => "not_found",
+                        409 => "conflict",
+                        422 => "invalid_body",
+                        _ => "internal_error",
+                    },
+                    message: status.reason_lossy().to_string(),
+                },
+            ),
+        }
+    }
+}
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> Self {
+        ApiError::Status(status)
+    }
+}
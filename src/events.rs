@@ -0,0 +1,231 @@
+use crate::urls::{LiveClick, Url};
+use rocket::{
+    futures::{SinkExt, StreamExt},
+    request::{FromRequest, Outcome},
+    response::stream::{self as sse, EventStream},
+    serde::{json, uuid::Uuid, Serialize},
+    tokio::{self, sync::broadcast},
+    Request, Shutdown,
+};
+use rocket_ws::{Channel, Message, WebSocket};
+use std::{collections::VecDeque, convert::Infallible, sync::Mutex};
+
+/// Event about a link, delivered to everyone who can see the link.
+#[derive(Clone)]
+pub struct Event {
+    pub id: u64,
+    pub url_id: Uuid,
+    pub creator: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub payload: Payload,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Payload {
+    Click(LiveClick),
+    LinkCreated(Url),
+    LinkUpdated(Url),
+    LinkDeleted { id: Uuid },
+}
+
+impl Payload {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Payload::Click(_) => "click",
+            Payload::LinkCreated(_) => "link-created",
+            Payload::LinkUpdated(_) => "link-updated",
+            Payload::LinkDeleted { .. } => "link-deleted",
+        }
+    }
+}
+
+/// In-process fan-out of events to the live connections, keeping the latest
+/// ones around so that reconnecting clients can catch up.
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+    history: Mutex<History>,
+}
+
+struct History {
+    next_id: u64,
+    events: VecDeque<Event>,
+    capacity: usize,
+}
+
+impl Events {
+    pub fn new(capacity: usize, replay_size: usize) -> Self {
+        // Ids start from the current time, so that they keep growing across
+        // restarts and stale ids from clients do not match new events
+        let next_id = chrono::Utc::now().timestamp_micros() as u64;
+
+        Events {
+            sender: broadcast::channel(capacity).0,
+            history: Mutex::new(History {
+                next_id,
+                events: VecDeque::with_capacity(replay_size),
+                capacity: replay_size,
+            }),
+        }
+    }
+
+    pub fn publish(&self, mut event: Event) {
+        let mut history = self.history.lock().unwrap();
+
+        event.id = history.next_id;
+        history.next_id += 1;
+
+        if history.capacity > 0 {
+            if history.events.len() == history.capacity {
+                history.events.pop_front();
+            }
+
+            history.events.push_back(event.clone());
+        }
+
+        // Sending only fails when nobody is listening
+        self.sender.send(event).ok();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribes to new events, also returning the buffered events published
+    /// after the one with id `last_id`.
+    pub fn resume(&self, last_id: u64) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let history = self.history.lock().unwrap();
+        let missed = history
+            .events
+            .iter()
+            .filter(|e| e.id > last_id)
+            .cloned()
+            .collect();
+
+        (missed, self.sender.subscribe())
+    }
+}
+
+/// Events a connection is interested in, either those of a single link or
+/// those of every link a user can see, leaving out clicks unless the user may
+/// see them. Workspaces are resolved once, when the connection is opened.
+pub enum Audience {
+    Url(Uuid),
+    User {
+        id: Uuid,
+        workspaces: Vec<Uuid>,
+        clicks: bool,
+    },
+}
+
+impl Audience {
+    pub fn includes(&self, event: &Event) -> bool {
+        match self {
+            Audience::Url(id) => event.url_id == *id,
+            Audience::User {
+                id,
+                workspaces,
+                clicks,
+            } => {
+                (*clicks || !matches!(event.payload, Payload::Click(_)))
+                    && match event.workspace_id {
+                        Some(workspace_id) => workspaces.contains(&workspace_id),
+                        None => event.creator == *id,
+                    }
+            }
+        }
+    }
+}
+
+/// Pushes each event of the audience as a JSON text message, until either the
+/// client closes the connection or the server shuts down. Messages from the
+/// client are ignored.
+pub fn channel(
+    ws: WebSocket,
+    mut events: broadcast::Receiver<Event>,
+    audience: Audience,
+    mut shutdown: Shutdown,
+) -> Channel<'static> {
+    ws.channel(move |mut stream| {
+        Box::pin(async move {
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) if audience.includes(&event) => {
+                            let text = json::to_string(&event.payload).unwrap_or_default();
+                            stream.send(Message::Text(text)).await?;
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => {}
+                    },
+                    _ = &mut shutdown => break,
+                }
+            }
+
+            Ok(())
+        })
+    })
+}
+
+/// Same as [`channel`], but as server-sent events named after their type,
+/// starting with the buffered events the client missed since `last_id`.
+pub fn event_stream(
+    events: &Events,
+    last_id: Option<u64>,
+    audience: Audience,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let (missed, mut events) = match last_id {
+        Some(id) => events.resume(id),
+        None => (Vec::new(), events.subscribe()),
+    };
+
+    EventStream! {
+        for event in missed.into_iter().filter(|e| audience.includes(e)) {
+            yield to_sse(&event);
+        }
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            if audience.includes(&event) {
+                yield to_sse(&event);
+            }
+        }
+    }
+}
+
+fn to_sse(event: &Event) -> sse::Event {
+    sse::Event::json(&event.payload)
+        .event(event.payload.name())
+        .id(event.id.to_string())
+}
+
+/// Id of the last event received, sent by clients when reconnecting.
+pub struct LastEventId(pub Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let id = req
+            .headers()
+            .get_one("Last-Event-ID")
+            .and_then(|id| id.trim().parse().ok());
+
+        Outcome::Success(LastEventId(id))
+    }
+}
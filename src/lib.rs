@@ -1,9 +1,191 @@
+use crate::{
+    auth::handlers::{
+        create_token, get_profile, get_tokens, logout, patch_profile, refresh, revoke_token,
+        signin, signup,
+    },
+    config::Config,
+    db::Db,
+    events::Events,
+    tags::handlers::{create_tag, delete_tag, get_tags, rename_tag},
+    urls::{
+        codes,
+        handlers::{
+            accept_transfer, cancel_transfer, create_url, create_urls_csv, create_urls_json,
+            decline_transfer, delete_url, export_urls, get_events, get_live, get_transfers,
+            get_trash, get_url, get_url_clicks, get_url_live, get_url_qr, get_url_revisions,
+            get_url_stats, get_urls_by_username, get_urls_by_workspace, get_user_stats,
+            import_urls, patch_url, preview, qr_code, redirect, restore_url, restore_url_revision,
+            search_urls, transfer_url, unlock, unlock_json,
+        },
+        tasks,
+    },
+    webhooks::{
+        handlers::{
+            create_webhook, delete_webhook, get_deliveries, get_webhook, get_webhooks,
+            patch_webhook, redeliver, rotate_webhook_secret,
+        },
+        tasks as webhook_tasks,
+    },
+    workspaces::handlers::{
+        add_member, create_workspace, delete_workspace, get_members, get_workspace, get_workspaces,
+        patch_member, remove_member, rename_workspace,
+    },
+};
+use rocket::{fairing::AdHoc, figment::Figment, http::Method, routes, Build, Rocket};
+use rocket_cors::{AllowedOrigins, CorsOptions};
+use rocket_db_pools::Database;
+
 pub mod auth;
 pub mod config;
 pub mod db;
+pub mod error;
+pub mod events;
+pub mod tags;
 pub mod urls;
 pub mod utils;
+pub mod webhooks;
+pub mod workspaces;
 
 pub trait Validate {
     fn validate(&self) -> bool;
 }
+
+/// Assembles the application from the given configuration, which is expected
+/// to hold both the Rocket settings and the [`config::Config`] ones.
+pub fn build(figment: Figment) -> Rocket<Build> {
+    let cors = CorsOptions::default()
+        .allowed_origins(AllowedOrigins::all())
+        .allowed_methods(
+            vec![Method::Get, Method::Post, Method::Patch, Method::Delete]
+                .into_iter()
+                .map(From::from)
+                .collect(),
+        )
+        .allow_credentials(true);
+
+    rocket::custom(figment)
+        .attach(AdHoc::config::<Config>())
+        .attach(AdHoc::try_on_ignite("Config Check", |rocket| async {
+            match rocket.state::<Config>().unwrap().check() {
+                Ok(()) => Ok(rocket),
+                Err(e) => {
+                    rocket::error!("{}", e);
+                    Err(rocket)
+                }
+            }
+        }))
+        .attach(AdHoc::try_on_ignite("Short URL Codes", |rocket| async {
+            match codes::from_config(rocket.state::<Config>().unwrap()) {
+                Ok(generator) => Ok(rocket.manage(generator)),
+                Err(e) => {
+                    rocket::error!("{}", e);
+                    Err(rocket)
+                }
+            }
+        }))
+        .attach(AdHoc::try_on_ignite("Live Events", |rocket| async {
+            let config = rocket.state::<Config>().unwrap();
+
+            // The remaining fairings still run after the config check fails,
+            // and a zero capacity would panic here
+            if config.check().is_err() {
+                return Err(rocket);
+            }
+
+            let events = Events::new(config.live_events_capacity, config.live_replay_size);
+            Ok(rocket.manage(events))
+        }))
+        .attach(cors.to_cors().unwrap())
+        .attach(Db::init())
+        .attach(auth::challenge())
+        .attach(tasks::expiration())
+        .attach(tasks::purge())
+        .attach(webhook_tasks::dispatch())
+        .attach(webhook_tasks::prune())
+        .mount(
+            "/auth",
+            routes![
+                signup,
+                signin,
+                refresh,
+                logout,
+                get_tokens,
+                create_token,
+                revoke_token
+            ],
+        )
+        .mount(
+            "/urls",
+            routes![
+                search_urls,
+                get_url,
+                get_url_clicks,
+                get_url_revisions,
+                get_url_qr,
+                get_url_live,
+                get_url_stats,
+                restore_url_revision,
+                create_url,
+                create_urls_json,
+                create_urls_csv,
+                import_urls,
+                patch_url,
+                delete_url,
+                get_trash,
+                restore_url,
+                transfer_url,
+                get_transfers,
+                accept_transfer,
+                decline_transfer,
+                cancel_transfer
+            ],
+        )
+        .mount(
+            "/tags",
+            routes![get_tags, create_tag, rename_tag, delete_tag],
+        )
+        .mount(
+            "/users",
+            routes![
+                get_profile,
+                patch_profile,
+                get_urls_by_username,
+                get_user_stats,
+                get_live,
+                get_events,
+                export_urls
+            ],
+        )
+        .mount(
+            "/webhooks",
+            routes![
+                get_webhooks,
+                create_webhook,
+                get_webhook,
+                patch_webhook,
+                rotate_webhook_secret,
+                delete_webhook,
+                get_deliveries,
+                redeliver
+            ],
+        )
+        .mount(
+            "/workspaces",
+            routes![
+                get_workspaces,
+                create_workspace,
+                get_workspace,
+                rename_workspace,
+                delete_workspace,
+                get_members,
+                add_member,
+                patch_member,
+                remove_member,
+                get_urls_by_workspace
+            ],
+        )
+        .mount(
+            "/",
+            routes![preview, redirect, qr_code, unlock, unlock_json],
+        )
+}
@@ -0,0 +1,31 @@
+use crate::Validate;
+use rocket::serde::{uuid::Uuid, Deserialize, Serialize};
+use rocket_db_pools::sqlx;
+
+pub mod handlers;
+mod repo;
+pub(crate) mod validators;
+
+#[derive(Deserialize, Serialize, sqlx::FromRow)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    id: Uuid,
+    #[serde(skip_serializing)]
+    owner: Uuid,
+    name: String,
+    created_at: sqlx::types::chrono::NaiveDateTime,
+    urls: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TagBody {
+    name: String,
+}
+
+impl Validate for TagBody {
+    fn validate(&self) -> bool {
+        validators::is_valid_tag(&self.name)
+    }
+}
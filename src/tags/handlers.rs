@@ -0,0 +1,105 @@
+use super::{repo, Tag, TagBody};
+use crate::{
+    auth::{
+        scopes::{UrlsRead, UrlsWrite},
+        Scoped,
+    },
+    db::Db,
+    error::ApiError,
+    Validate,
+};
+use rocket::{http::Status, serde::json::Json};
+use rocket_db_pools::Connection;
+use sqlx::types::Uuid;
+
+#[rocket::get("/")]
+pub async fn get_tags(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+) -> Result<Json<Vec<Tag>>, Status> {
+    let tags = repo::get_tags_by_owner(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(tags))
+}
+
+#[rocket::post("/", data = "<body>")]
+pub async fn create_tag(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    body: Json<TagBody>,
+) -> Result<Json<Tag>, ApiError> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    let tag = repo::insert_tag(&mut db, user.id, &body.name)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or_else(|| tag_taken(&body.name))?;
+
+    Ok(Json(tag))
+}
+
+#[rocket::patch("/<id>", data = "<body>")]
+pub async fn rename_tag(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+    body: Json<TagBody>,
+) -> Result<Json<Tag>, ApiError> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    let tag = repo::get_tag(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if tag.owner != user.id {
+        return Err(Status::Forbidden.into());
+    }
+
+    let tag = repo::rename_tag(&mut db, id, &body.name)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(e) if e.is_unique_violation() => tag_taken(&body.name),
+            _ => Status::InternalServerError.into(),
+        })?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(tag))
+}
+
+#[rocket::delete("/<id>")]
+pub async fn delete_tag(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+) -> Result<Json<Tag>, Status> {
+    let tag = repo::get_tag(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if tag.owner != user.id {
+        return Err(Status::Forbidden);
+    }
+
+    let tag = repo::delete_tag(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(tag))
+}
+
+fn tag_taken(name: &str) -> ApiError {
+    ApiError::new(
+        Status::Conflict,
+        "tag_taken",
+        format!("The tag \"{name}\" already exists"),
+    )
+}
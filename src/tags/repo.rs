@@ -0,0 +1,114 @@
+use super::Tag;
+use sqlx::{types::Uuid, PgConnection};
+
+pub async fn get_tags_by_owner(
+    db: &mut PgConnection,
+    owner: Uuid,
+) -> Result<Vec<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT
+            tags.id, tags.owner, tags.name, tags.created_at,
+            count(urls.id) AS "urls!"
+        FROM tags
+        LEFT JOIN url_tags ON url_tags.tag_id = tags.id
+        LEFT JOIN urls ON urls.id = url_tags.url_id AND urls.deleted_at IS NULL
+        WHERE tags.owner = $1
+        GROUP BY tags.id
+        ORDER BY tags.name;
+        "#,
+        owner,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_tag(db: &mut PgConnection, id: Uuid) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT
+            id, owner, name, created_at,
+            (
+                SELECT count(*)
+                FROM url_tags
+                JOIN urls ON urls.id = url_tags.url_id
+                WHERE url_tags.tag_id = tags.id AND urls.deleted_at IS NULL
+            ) AS "urls!"
+        FROM tags
+        WHERE id = $1;
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn insert_tag(
+    db: &mut PgConnection,
+    owner: Uuid,
+    name: &str,
+) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        INSERT INTO tags (owner, name)
+        VALUES ($1, $2)
+        ON CONFLICT (owner, name) DO NOTHING
+        RETURNING id, owner, name, created_at, 0::bigint AS "urls!";
+        "#,
+        owner,
+        name,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn rename_tag(
+    db: &mut PgConnection,
+    id: Uuid,
+    name: &str,
+) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        UPDATE tags SET
+            name = $2
+        WHERE id = $1
+        RETURNING
+            id, owner, name, created_at,
+            (
+                SELECT count(*)
+                FROM url_tags
+                JOIN urls ON urls.id = url_tags.url_id
+                WHERE url_tags.tag_id = tags.id AND urls.deleted_at IS NULL
+            ) AS "urls!";
+        "#,
+        id,
+        name,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn delete_tag(db: &mut PgConnection, id: Uuid) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+        DELETE FROM tags
+        WHERE id = $1
+        RETURNING
+            id, owner, name, created_at,
+            (
+                SELECT count(*)
+                FROM url_tags
+                JOIN urls ON urls.id = url_tags.url_id
+                WHERE url_tags.tag_id = tags.id AND urls.deleted_at IS NULL
+            ) AS "urls!";
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
@@ -1,19 +1,259 @@
-use rocket::serde::{Deserialize, Serialize};
+use chrono::TimeDelta;
+use chrono_tz::Tz;
+use rocket::{
+    form::{self, ValueField},
+    http::{Accept, ContentType, Header, MediaType, Status},
+    request::{FromParam, FromRequest, Outcome},
+    response::{content::RawHtml, Redirect},
+    serde::{json::Json, Deserialize, Serialize},
+    FromForm, FromFormField, Request, Responder,
+};
+use sha2::{Digest, Sha256};
 use sqlx::types::{chrono::NaiveDateTime, Uuid};
-use validators::{is_valid_description, is_valid_long_url, is_valid_title};
+use std::convert::Infallible;
+use validators::{
+    is_valid_alias, is_valid_description, is_valid_expiration, is_valid_link_password,
+    is_valid_long_url, is_valid_max_visits, is_valid_tag_list, is_valid_title,
+};
+use woothee::{parser::Parser, woothee::VALUE_UNKNOWN};
 
-use crate::Validate;
+use crate::{
+    auth::InsufficientScope,
+    config::Config,
+    error::{ApiError, ErrorBody},
+    events::{Event, Payload},
+    utils::{deserialize_some, serialize_is_some, Timestamp},
+    Validate,
+};
 
+pub mod codes;
 pub mod handlers;
+mod import;
+mod pages;
+mod qr;
 mod repo;
+pub mod tasks;
 mod validators;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, sqlx::FromRow, Clone)]
 #[serde(crate = "rocket::serde")]
 #[serde(rename_all = "camelCase")]
 pub struct Url {
     id: Uuid,
     creator: Uuid,
+    workspace_id: Option<Uuid>,
+    title: String,
+    description: String,
+    long_url: String,
+    short_url: String,
+    times_visited: i32,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    max_visits: Option<i32>,
+    fallback_url: Option<String>,
+    active: bool,
+    #[serde(
+        rename = "passwordProtected",
+        serialize_with = "serialize_is_some",
+        skip_deserializing
+    )]
+    password: Option<String>,
+    preview: bool,
+    visibility: Visibility,
+    deleted_at: Option<NaiveDateTime>,
+    tags: Vec<String>,
+}
+
+impl Url {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|e| e <= chrono::Utc::now().naive_utc())
+            || self.max_visits.is_some_and(|m| self.times_visited >= m)
+    }
+
+    fn event(&self, payload: Payload) -> Event {
+        Event {
+            id: 0,
+            url_id: self.id,
+            creator: self.creator,
+            workspace_id: self.workspace_id,
+            payload,
+        }
+    }
+}
+
+pub enum ListScope<'a> {
+    User { username: &'a str, viewer: Uuid },
+    Workspace(Uuid),
+}
+
+#[derive(Deserialize, Serialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "url_visibility", rename_all = "lowercase")]
+pub enum Visibility {
+    Private,
+    Unlisted,
+    Public,
+}
+
+impl Visibility {
+    fn as_str(self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Unlisted => "unlisted",
+            Visibility::Public => "public",
+        }
+    }
+}
+
+#[derive(FromFormField, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[field(value = "created_at")]
+    CreatedAt,
+    #[field(value = "updated_at")]
+    UpdatedAt,
+    #[field(value = "times_visited")]
+    TimesVisited,
+    #[field(value = "title")]
+    Title,
+}
+
+#[derive(FromFormField, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(FromFormField, Clone, Copy)]
+pub enum TagMatch {
+    Any,
+    All,
+}
+
+/// Deduplicated tag names and how many of them a link must carry to match.
+fn tag_requirement(tags: &[String], tag_match: TagMatch) -> (Vec<String>, i64) {
+    let mut tags = tags.to_vec();
+    tags.sort();
+    tags.dedup();
+
+    let min_matches = match tag_match {
+        TagMatch::Any => 1,
+        TagMatch::All => tags.len() as i64,
+    };
+
+    (tags, min_matches)
+}
+
+#[derive(FromForm)]
+pub struct SearchQuery {
+    q: String,
+    all: Option<bool>,
+    limit: Option<u16>,
+    #[field(name = "tag")]
+    tags: Vec<String>,
+    #[field(default = TagMatch::Any)]
+    tag_match: TagMatch,
+}
+
+#[derive(FromForm)]
+pub struct ListQuery {
+    #[field(default = SortKey::CreatedAt)]
+    sort: SortKey,
+    #[field(default = SortOrder::Desc)]
+    order: SortOrder,
+    cursor: Option<String>,
+    limit: Option<u16>,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    q: Option<String>,
+    #[field(name = "tag")]
+    tags: Vec<String>,
+    #[field(default = TagMatch::Any)]
+    tag_match: TagMatch,
+}
+
+/// Position right after the last item of a page, in terms of the sort key
+/// value and the id that breaks ties between equal values.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Cursor {
+    sort: SortKey,
+    order: SortOrder,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn after(url: &Url, sort: SortKey, order: SortOrder) -> Self {
+        let value = match sort {
+            SortKey::CreatedAt => url.created_at.to_string(),
+            SortKey::UpdatedAt => url.updated_at.to_string(),
+            SortKey::TimesVisited => url.times_visited.to_string(),
+            SortKey::Title => url.title.clone(),
+        };
+
+        Cursor {
+            sort,
+            order,
+            value,
+            id: url.id,
+        }
+    }
+
+    fn encode(&self) -> String {
+        hex::encode(rocket::serde::json::to_string(self).unwrap())
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let json = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        rocket::serde::json::from_str(&json).ok()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UrlPage {
+    urls: Vec<Url>,
+    next: Option<String>,
+}
+
+#[derive(FromFormField, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    fn negotiate(accept: Option<&Accept>) -> Self {
+        match accept.map(|a| a.preferred().media_type()) {
+            Some(m) if *m == MediaType::CSV => ExportFormat::Csv,
+            Some(m) if m.top() == "application" && m.sub() == "x-ndjson" => ExportFormat::Ndjson,
+            _ => ExportFormat::Json,
+        }
+    }
+
+    fn content_type(self) -> ContentType {
+        match self {
+            ExportFormat::Csv => ContentType::CSV,
+            ExportFormat::Json => ContentType::JSON,
+            ExportFormat::Ndjson => ContentType::new("application", "x-ndjson"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRow {
+    id: Uuid,
+    workspace_id: Option<Uuid>,
     title: String,
     description: String,
     long_url: String,
@@ -21,6 +261,360 @@ pub struct Url {
     times_visited: i32,
     created_at: NaiveDateTime,
     updated_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    max_visits: Option<i32>,
+    fallback_url: Option<String>,
+    active: bool,
+    password_protected: bool,
+    preview: bool,
+    visibility: Visibility,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clicks: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_clicked_at: Option<NaiveDateTime>,
+}
+
+impl ExportRow {
+    const CSV_HEADER: [&'static str; 17] = [
+        "id",
+        "workspace_id",
+        "title",
+        "description",
+        "long_url",
+        "short_url",
+        "times_visited",
+        "created_at",
+        "updated_at",
+        "expires_at",
+        "max_visits",
+        "fallback_url",
+        "active",
+        "password_protected",
+        "preview",
+        "visibility",
+        "tags",
+    ];
+    const CSV_CLICKS_HEADER: [&'static str; 2] = ["clicks", "last_clicked_at"];
+
+    fn csv_header(with_clicks: bool) -> Vec<&'static str> {
+        let mut header = Self::CSV_HEADER.to_vec();
+
+        if with_clicks {
+            header.extend(Self::CSV_CLICKS_HEADER);
+        }
+
+        header
+    }
+
+    fn csv_record(&self, with_clicks: bool) -> Vec<String> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+
+        fn time(value: &NaiveDateTime) -> String {
+            value.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+        }
+
+        let mut record = vec![
+            self.id.to_string(),
+            opt(&self.workspace_id),
+            self.title.clone(),
+            self.description.clone(),
+            self.long_url.clone(),
+            self.short_url.clone(),
+            self.times_visited.to_string(),
+            time(&self.created_at),
+            time(&self.updated_at),
+            self.expires_at.as_ref().map(time).unwrap_or_default(),
+            opt(&self.max_visits),
+            opt(&self.fallback_url),
+            self.active.to_string(),
+            self.password_protected.to_string(),
+            self.preview.to_string(),
+            self.visibility.as_str().to_string(),
+            self.tags.join(" "),
+        ];
+
+        if with_clicks {
+            record.push(opt(&self.clicks));
+            record.push(self.last_clicked_at.as_ref().map(time).unwrap_or_default());
+        }
+
+        record
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Click {
+    id: i64,
+    url_id: Uuid,
+    referrer: Option<String>,
+    user_agent: Option<String>,
+    ip_hash: Option<String>,
+    accept_language: Option<String>,
+    created_at: NaiveDateTime,
+    country: Option<String>,
+    browser: Option<String>,
+    os: Option<String>,
+}
+
+/// Click pushed to live connections as it happens.
+#[derive(Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct LiveClick {
+    url_id: Uuid,
+    short_url: String,
+    times_visited: i32,
+    referrer: Option<String>,
+    country: Option<String>,
+    browser: Option<String>,
+    os: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+impl LiveClick {
+    fn new(url: &Url, click: Click) -> Self {
+        LiveClick {
+            url_id: url.id,
+            short_url: url.short_url.clone(),
+            times_visited: url.times_visited,
+            referrer: click.referrer,
+            country: click.country,
+            browser: click.browser,
+            os: click.os,
+            created_at: click.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    id: i64,
+    url_id: Uuid,
+    editor: Uuid,
+    old_long_url: Option<String>,
+    new_long_url: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(FromFormField, Serialize, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum StatsInterval {
+    Hour,
+    Day,
+    Week,
+}
+
+impl StatsInterval {
+    fn as_str(self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+
+    fn duration(self) -> TimeDelta {
+        match self {
+            StatsInterval::Hour => TimeDelta::hours(1),
+            StatsInterval::Day => TimeDelta::days(1),
+            StatsInterval::Week => TimeDelta::weeks(1),
+        }
+    }
+}
+
+/// IANA time zone name, such as `America/Sao_Paulo`.
+pub struct StatsTimezone(Tz);
+
+impl<'v> form::FromFormField<'v> for StatsTimezone {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        field
+            .value
+            .parse::<Tz>()
+            .map(StatsTimezone)
+            .map_err(|_| form::Error::validation("invalid time zone").into())
+    }
+}
+
+#[derive(FromForm)]
+pub struct StatsQuery {
+    #[field(default = StatsInterval::Day)]
+    interval: StatsInterval,
+    #[field(default = StatsTimezone(Tz::UTC))]
+    tz: StatsTimezone,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    #[field(default = 10, validate = range(1..=100))]
+    top: u16,
+}
+
+#[derive(Clone, Copy)]
+pub enum StatsScope {
+    Url(Uuid),
+    User(Uuid),
+}
+
+#[derive(Clone, Copy)]
+pub enum StatsDimension {
+    Referrer,
+    Country,
+    Browser,
+    Os,
+}
+
+impl StatsDimension {
+    fn as_str(self) -> &'static str {
+        match self {
+            StatsDimension::Referrer => "referrer",
+            StatsDimension::Country => "country",
+            StatsDimension::Browser => "browser",
+            StatsDimension::Os => "os",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct StatsBucket {
+    bucket: NaiveDateTime,
+    clicks: i64,
+}
+
+/// Click count for a value of some dimension, where a missing value stands
+/// for direct visits or for visitors that could not be identified.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct StatsEntry {
+    name: Option<String>,
+    clicks: i64,
+}
+
+/// Click analytics over a time range, given in the requested time zone.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    interval: StatsInterval,
+    timezone: &'static str,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    clicks: i64,
+    unique_visitors: i64,
+    series: Vec<StatsBucket>,
+    referrers: Vec<StatsEntry>,
+    countries: Vec<StatsEntry>,
+    browsers: Vec<StatsEntry>,
+    operating_systems: Vec<StatsEntry>,
+}
+
+#[derive(Deserialize, Serialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "transfer_status", rename_all = "lowercase")]
+pub enum TransferStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Transfer {
+    id: Uuid,
+    url_id: Uuid,
+    from_user: Uuid,
+    to_user: Option<Uuid>,
+    to_workspace: Option<Uuid>,
+    status: TransferStatus,
+    created_at: NaiveDateTime,
+    resolved_at: Option<NaiveDateTime>,
+}
+
+pub struct Visitor {
+    referrer: Option<String>,
+    user_agent: Option<String>,
+    ip_hash: Option<String>,
+    accept_language: Option<String>,
+    country: Option<String>,
+    browser: Option<String>,
+    os: Option<String>,
+}
+
+impl Visitor {
+    /// Two-letter country code, taken from the header set by a trusted proxy
+    /// when one is configured, or else from the region of the preferred
+    /// language.
+    fn country(header: Option<&str>, accept_language: Option<&str>) -> Option<String> {
+        let is_code = |c: &&str| c.len() == 2 && c.chars().all(|c| c.is_ascii_alphabetic());
+
+        header
+            .map(str::trim)
+            .filter(is_code)
+            .filter(|c| !c.eq_ignore_ascii_case("XX"))
+            .or_else(|| {
+                accept_language?
+                    .split([',', ';'])
+                    .next()?
+                    .trim()
+                    .split(['-', '_'])
+                    .nth(1)
+                    .filter(is_code)
+            })
+            .map(str::to_ascii_uppercase)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Visitor {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = req.rocket().state::<Config>().unwrap();
+        let header = |name: &str, max_len: usize| {
+            req.headers()
+                .get_one(name)
+                .map(|v| v.chars().take(max_len).collect::<String>())
+        };
+
+        let ip_hash = req.client_ip().map(|ip| {
+            let mut hasher = Sha256::new();
+            hasher.update(config.ip_hash_secret.as_bytes());
+            hasher.update(ip.to_string().as_bytes());
+            hex::encode(hasher.finalize())
+        });
+
+        let user_agent = header("User-Agent", 512);
+        let accept_language = header("Accept-Language", 256);
+        let agent = user_agent.as_deref().and_then(|ua| Parser::new().parse(ua));
+        let known = |name: &str| (name != VALUE_UNKNOWN).then(|| name.to_string());
+
+        let country = Visitor::country(
+            config
+                .country_header
+                .as_deref()
+                .and_then(|name| req.headers().get_one(name)),
+            accept_language.as_deref(),
+        );
+
+        Outcome::Success(Visitor {
+            referrer: header("Referer", 2048),
+            browser: agent.as_ref().and_then(|a| known(a.name)),
+            os: agent.as_ref().and_then(|a| known(a.os)),
+            user_agent,
+            ip_hash,
+            accept_language,
+            country,
+        })
+    }
 }
 
 #[derive(Deserialize)]
@@ -30,6 +624,15 @@ pub struct CreateBody {
     title: String,
     description: String,
     long_url: String,
+    alias: Option<String>,
+    workspace_id: Option<Uuid>,
+    expires_at: Option<NaiveDateTime>,
+    max_visits: Option<i32>,
+    fallback_url: Option<String>,
+    password: Option<String>,
+    preview: Option<bool>,
+    visibility: Option<Visibility>,
+    tags: Option<Vec<String>>,
 }
 
 impl Validate for CreateBody {
@@ -37,23 +640,428 @@ impl Validate for CreateBody {
         is_valid_title(&self.title)
             && is_valid_long_url(&self.long_url)
             && is_valid_description(&self.description)
+            && self.alias.as_deref().is_none_or(is_valid_alias)
+            && self.expires_at.is_none_or(is_valid_expiration)
+            && self.max_visits.is_none_or(is_valid_max_visits)
+            && self.fallback_url.as_deref().is_none_or(is_valid_long_url)
+            && self.password.as_deref().is_none_or(is_valid_link_password)
+            && self.tags.as_deref().is_none_or(is_valid_tag_list)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CsvRow {
+    title: String,
+    description: String,
+    long_url: String,
+    alias: Option<String>,
+}
+
+impl From<CsvRow> for CreateBody {
+    fn from(row: CsvRow) -> Self {
+        CreateBody {
+            title: row.title,
+            description: row.description,
+            long_url: row.long_url,
+            alias: row.alias.filter(|a| !a.is_empty()),
+            workspace_id: None,
+            expires_at: None,
+            max_visits: None,
+            fallback_url: None,
+            password: None,
+            preview: None,
+            visibility: None,
+            tags: None,
+        }
+    }
+}
+
+#[derive(FromFormField, Clone, Copy, PartialEq)]
+pub enum BulkMode {
+    #[field(value = "atomic")]
+    Atomic,
+    #[field(value = "best_effort")]
+    BestEffort,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct BulkResult {
+    row: usize,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias_conflict: Option<String>,
+}
+
+impl BulkResult {
+    fn created(row: usize, url: Url) -> Self {
+        BulkResult {
+            row,
+            status: Status::Ok.code,
+            url: Some(url),
+            error: None,
+            alias_conflict: None,
+        }
+    }
+
+    fn failed(row: usize, error: ApiError) -> Self {
+        let (status, body) = error.into_parts();
+
+        BulkResult {
+            row,
+            status: status.code,
+            url: None,
+            error: Some(body),
+            alias_conflict: None,
+        }
+    }
+
+    fn with_alias_conflict(mut self, alias: Option<String>) -> Self {
+        self.alias_conflict = alias;
+        self
+    }
+
+    fn discard(&mut self) {
+        self.url = None;
+    }
+
+    fn roll_back(&mut self) {
+        if self.url.take().is_some() {
+            self.status = Status::FailedDependency.code;
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct BulkReport {
+    committed: bool,
+    dry_run: bool,
+    created: usize,
+    failed: usize,
+    results: Vec<BulkResult>,
+}
+
+pub struct BulkOptions {
+    mode: BulkMode,
+    dry_run: bool,
+    alias_fallback: bool,
+}
+
+impl BulkOptions {
+    fn new(mode: BulkMode) -> Self {
+        BulkOptions {
+            mode,
+            dry_run: false,
+            alias_fallback: false,
+        }
     }
 }
 
+#[derive(FromFormField, Clone, Copy)]
+pub enum ImportSource {
+    #[field(value = "bookmarks")]
+    Bookmarks,
+    #[field(value = "csv")]
+    Csv,
+}
+
+/// Column layouts of the CSV exports of some well known shorteners.
+#[derive(FromFormField, Clone, Copy)]
+pub enum ImportPreset {
+    #[field(value = "generic")]
+    Generic,
+    #[field(value = "urlessen")]
+    Urlessen,
+    #[field(value = "bitly")]
+    Bitly,
+    #[field(value = "rebrandly")]
+    Rebrandly,
+}
+
+#[derive(FromForm)]
+pub struct ImportQuery {
+    source: ImportSource,
+    #[field(default = ImportPreset::Generic)]
+    preset: ImportPreset,
+    url_column: Option<String>,
+    title_column: Option<String>,
+    description_column: Option<String>,
+    alias_column: Option<String>,
+    tags_column: Option<String>,
+    #[field(default = false)]
+    dry_run: bool,
+    #[field(default = BulkMode::BestEffort)]
+    mode: BulkMode,
+}
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 #[serde(rename_all = "camelCase")]
 pub struct PatchBody {
     title: Option<String>,
     description: Option<String>,
+    long_url: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    expires_at: Option<Option<NaiveDateTime>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    max_visits: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    fallback_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    password: Option<Option<String>>,
+    preview: Option<bool>,
+    visibility: Option<Visibility>,
+    tags: Option<Vec<String>>,
 }
 
 impl Validate for PatchBody {
     fn validate(&self) -> bool {
-        self.title.as_ref().map_or(true, |t| is_valid_title(t))
+        self.title.as_deref().is_none_or(is_valid_title)
+            && self.description.as_deref().is_none_or(is_valid_description)
+            && self.long_url.as_deref().is_none_or(is_valid_long_url)
+            && self.expires_at.flatten().is_none_or(is_valid_expiration)
+            && self.max_visits.flatten().is_none_or(is_valid_max_visits)
             && self
-                .description
+                .fallback_url
                 .as_ref()
-                .map_or(true, |d| is_valid_description(d))
+                .and_then(Option::as_deref)
+                .is_none_or(is_valid_long_url)
+            && self
+                .password
+                .as_ref()
+                .and_then(Option::as_deref)
+                .is_none_or(is_valid_link_password)
+            && self.tags.as_deref().is_none_or(is_valid_tag_list)
     }
 }
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct TransferBody {
+    to_user: Option<String>,
+    to_workspace: Option<Uuid>,
+}
+
+impl Validate for TransferBody {
+    fn validate(&self) -> bool {
+        self.to_user.is_some() != self.to_workspace.is_some()
+    }
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(crate = "rocket::serde")]
+pub struct UnlockBody {
+    password: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Preview {
+    short_url: String,
+    long_url: Option<String>,
+    host: Option<String>,
+    #[serde(flatten)]
+    details: Option<PreviewDetails>,
+}
+
+/// What a preview tells about a link besides its destination, left out for
+/// private links and for links of users with a private profile.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewDetails {
+    title: String,
+    description: String,
+    creator: String,
+    times_visited: i32,
+    created_at: NaiveDateTime,
+}
+
+impl Preview {
+    fn new(url: Url, creator: String, show_destination: bool) -> Self {
+        Preview {
+            host: show_destination.then(|| host_of(&url.long_url)).flatten(),
+            long_url: show_destination.then_some(url.long_url),
+            short_url: url.short_url,
+            details: Some(PreviewDetails {
+                title: url.title,
+                description: url.description,
+                creator,
+                times_visited: url.times_visited,
+                created_at: url.created_at,
+            }),
+        }
+    }
+
+    fn limited(url: Url, show_destination: bool) -> Self {
+        Preview {
+            host: show_destination.then(|| host_of(&url.long_url)).flatten(),
+            long_url: None,
+            short_url: url.short_url,
+            details: None,
+        }
+    }
+}
+
+fn host_of(long_url: &str) -> Option<String> {
+    url::Url::parse(long_url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+}
+
+pub struct PreviewCode<'r>(&'r str);
+
+impl<'r> FromParam<'r> for PreviewCode<'r> {
+    type Error = &'r str;
+
+    fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        param.strip_suffix('+').map(PreviewCode).ok_or(param)
+    }
+}
+
+#[derive(Responder)]
+pub enum Visit {
+    Redirect(Redirect),
+    PreviewPage(RawHtml<String>),
+    PreviewJson(Json<Preview>),
+}
+
+#[derive(Responder)]
+pub enum VisitError {
+    #[response(status = 404)]
+    NotFound(RawHtml<&'static str>),
+    #[response(status = 410)]
+    Gone(RawHtml<String>),
+    #[response(status = 401)]
+    Locked(RawHtml<String>),
+    Api(ApiError),
+    Status(Status),
+}
+
+impl From<Status> for VisitError {
+    fn from(status: Status) -> Self {
+        VisitError::Status(status)
+    }
+}
+
+#[derive(Responder)]
+pub enum ExportError {
+    Scope(InsufficientScope),
+    Status(Status),
+}
+
+impl From<Status> for ExportError {
+    fn from(status: Status) -> Self {
+        ExportError::Status(status)
+    }
+}
+
+#[derive(FromFormField, Clone, Copy, PartialEq)]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    fn negotiate(accept: Option<&Accept>) -> Self {
+        match accept.map(|a| a.preferred().media_type()) {
+            Some(m) if *m == MediaType::SVG => QrFormat::Svg,
+            _ => QrFormat::Png,
+        }
+    }
+
+    fn content_type(self) -> ContentType {
+        match self {
+            QrFormat::Png => ContentType::PNG,
+            QrFormat::Svg => ContentType::SVG,
+        }
+    }
+}
+
+/// Error correction levels, recovering roughly 7%, 15%, 25% and 30% of the
+/// code respectively.
+#[derive(FromFormField, Clone, Copy)]
+pub enum QrLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+/// Color given as a `RRGGBB` or `RRGGBBAA` hex string.
+#[derive(Clone, Copy, PartialEq)]
+pub struct QrColor([u8; 4]);
+
+impl QrColor {
+    const BLACK: QrColor = QrColor([0, 0, 0, 255]);
+    const WHITE: QrColor = QrColor([255, 255, 255, 255]);
+
+    fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let mut rgba = [255; 4];
+
+        for (i, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+            *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+
+        Some(QrColor(rgba))
+    }
+}
+
+impl<'v> form::FromFormField<'v> for QrColor {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        QrColor::parse(field.value).ok_or_else(|| form::Error::validation("invalid color").into())
+    }
+}
+
+#[derive(FromForm)]
+pub struct QrQuery<'r> {
+    format: form::Result<'r, QrFormat>,
+    #[field(default = 256, validate = range(64..=2048))]
+    size: u32,
+    #[field(default = 4, validate = range(..=16))]
+    margin: u32,
+    #[field(default = QrLevel::M)]
+    ec: QrLevel,
+    #[field(default = QrColor::BLACK)]
+    fg: QrColor,
+    #[field(default = QrColor::WHITE)]
+    bg: QrColor,
+}
+
+pub struct IfNoneMatch<'r>(Option<&'r str>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch<'r> {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfNoneMatch(req.headers().get_one("If-None-Match")))
+    }
+}
+
+/// Rendered QR code. The body is left empty when answering with
+/// `304 Not Modified`.
+#[derive(Responder)]
+pub struct QrImage {
+    body: (Status, Vec<u8>),
+    content_type: ContentType,
+    cache_control: Header<'static>,
+    etag: Header<'static>,
+    last_modified: Header<'static>,
+}
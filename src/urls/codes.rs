@@ -0,0 +1,285 @@
+use crate::{
+    config::{Config, ShortUrlStrategy},
+    urls::repo,
+};
+use sqlx::PgConnection;
+
+#[rocket::async_trait]
+pub trait CodeGenerator: Send + Sync {
+    async fn generate(&self, db: &mut PgConnection) -> Result<String, CodeError>;
+}
+
+pub enum CodeError {
+    /// Every code of the configured length has been handed out.
+    Exhausted,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CodeError {
+    fn from(e: sqlx::Error) -> Self {
+        CodeError::Database(e)
+    }
+}
+
+pub fn from_config(config: &Config) -> Result<Box<dyn CodeGenerator>, String> {
+    let alphabet = config.short_url_alphabet.chars().collect::<Vec<_>>();
+    let length = config.short_url_length;
+
+    if !(1..=16).contains(&length) {
+        return Err(format!(
+            "invalid short_url_length {length}, expected a value between 1 and 16"
+        ));
+    }
+
+    if alphabet.len() < 2
+        || alphabet.len() > 64
+        || !alphabet
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        || (1..alphabet.len()).any(|i| alphabet[..i].contains(&alphabet[i]))
+    {
+        return Err(String::from(
+            "invalid short_url_alphabet, expected 2 to 64 unique alphanumeric, '-' or '_' characters",
+        ));
+    }
+
+    let base = alphabet.len() as u128;
+    let space = base
+        .checked_pow(length as u32)
+        .filter(|s| *s < 1 << 127)
+        .ok_or("short_url_alphabet and short_url_length yield too many codes")?;
+
+    match config.short_url_strategy {
+        ShortUrlStrategy::Random => Ok(Box::new(RandomCodes { alphabet, length })),
+        ShortUrlStrategy::Sequence => Ok(Box::new(SequenceCodes {
+            alphabet,
+            length,
+            space,
+        })),
+        ShortUrlStrategy::Hashid => {
+            let multiplier = multiplier_of(space, base);
+            let mut alphabet = alphabet;
+            let salt = config.short_url_salt.chars().collect::<Vec<_>>();
+            consistent_shuffle(&mut alphabet, &salt);
+
+            Ok(Box::new(HashidCodes {
+                alphabet,
+                length,
+                space,
+                multiplier,
+            }))
+        }
+    }
+}
+
+/// Random codes drawn from the alphabet. Collisions are possible and must be
+/// retried by the caller.
+pub struct RandomCodes {
+    alphabet: Vec<char>,
+    length: usize,
+}
+
+#[rocket::async_trait]
+impl CodeGenerator for RandomCodes {
+    async fn generate(&self, _db: &mut PgConnection) -> Result<String, CodeError> {
+        Ok(nanoid::format(
+            nanoid::rngs::default,
+            &self.alphabet,
+            self.length,
+        ))
+    }
+}
+
+/// Codes obtained by encoding the next value of `short_url_seq` in the base
+/// given by the alphabet, left padded up to the configured length.
+pub struct SequenceCodes {
+    alphabet: Vec<char>,
+    length: usize,
+    space: u128,
+}
+
+#[rocket::async_trait]
+impl CodeGenerator for SequenceCodes {
+    async fn generate(&self, db: &mut PgConnection) -> Result<String, CodeError> {
+        let n = repo::next_short_url_seq(db).await? as u128;
+
+        // Past the end of the space codes would grow beyond the configured
+        // length, up to the point of not fitting in the column
+        if n >= self.space {
+            return Err(CodeError::Exhausted);
+        }
+
+        Ok(encode(n, &self.alphabet, self.length))
+    }
+}
+
+/// Hashids-style codes: the next value of `short_url_seq` is scrambled with a
+/// bijection over the code space and encoded with a salt-shuffled alphabet, so
+/// consecutive codes do not look sequential.
+pub struct HashidCodes {
+    alphabet: Vec<char>,
+    length: usize,
+    space: u128,
+    multiplier: u128,
+}
+
+#[rocket::async_trait]
+impl CodeGenerator for HashidCodes {
+    async fn generate(&self, db: &mut PgConnection) -> Result<String, CodeError> {
+        let n = repo::next_short_url_seq(db).await? as u128;
+
+        // Past the end of the space the bijection would wrap around and hand
+        // out codes that were already issued
+        if n >= self.space {
+            return Err(CodeError::Exhausted);
+        }
+
+        let scrambled = mul_mod(n, self.multiplier, self.space);
+        Ok(encode(scrambled, &self.alphabet, self.length))
+    }
+}
+
+fn encode(mut n: u128, alphabet: &[char], length: usize) -> String {
+    let base = alphabet.len() as u128;
+    let mut code = Vec::with_capacity(length);
+
+    loop {
+        code.push(alphabet[(n % base) as usize]);
+        n /= base;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    code.resize(code.len().max(length), alphabet[0]);
+    code.iter().rev().collect()
+}
+
+fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    let (mut a, mut b, mut result) = (a % m, b % m, 0);
+
+    while b > 0 {
+        if b & 1 == 1 {
+            result = (result + a) % m;
+        }
+
+        a = (a << 1) % m;
+        b >>= 1;
+    }
+
+    result
+}
+
+/// Fibonacci hashing: a multiplier near space / phi that is coprime with the
+/// base, and therefore with the whole code space.
+fn multiplier_of(space: u128, base: u128) -> u128 {
+    let mut multiplier = (space as f64 * 0.618_033_988_749_895) as u128 | 1;
+    while gcd(multiplier, base) != 1 {
+        multiplier += 2;
+    }
+
+    multiplier
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn consistent_shuffle(alphabet: &mut [char], salt: &[char]) {
+    if salt.is_empty() {
+        return;
+    }
+
+    let mut v = 0;
+    let mut p = 0;
+
+    for i in (1..alphabet.len()).rev() {
+        v %= salt.len();
+        let n = salt[v] as usize;
+        p += n;
+        alphabet.swap(i, (n + v + p) % i);
+        v += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn chars(alphabet: &str) -> Vec<char> {
+        alphabet.chars().collect()
+    }
+
+    #[test]
+    fn codes_are_left_padded_up_to_the_length() {
+        let digits = chars("0123456789");
+
+        assert_eq!(encode(0, &digits, 4), "0000");
+        assert_eq!(encode(42, &digits, 4), "0042");
+        assert_eq!(encode(12345, &digits, 4), "12345");
+        assert_eq!(encode(5, &chars("ab"), 3), "bab");
+        assert_eq!(
+            encode(61, &chars(&Config::default().short_url_alphabet), 2),
+            "0z"
+        );
+    }
+
+    #[test]
+    fn scrambling_is_a_bijection_over_the_code_space() {
+        for (base, length) in [(2u128, 1u32), (2, 7), (10, 3), (36, 2), (62, 2)] {
+            let space = base.pow(length);
+            let multiplier = multiplier_of(space, base);
+            let scrambled = (0..space)
+                .map(|n| mul_mod(n, multiplier, space))
+                .collect::<HashSet<_>>();
+
+            assert_eq!(
+                scrambled.len() as u128,
+                space,
+                "base {base}, length {length}"
+            );
+            assert!(scrambled.iter().all(|n| *n < space));
+        }
+    }
+
+    #[test]
+    fn scrambling_does_not_overflow_large_spaces() {
+        let space = 64u128.pow(16);
+        let multiplier = multiplier_of(space, 64);
+
+        assert!(mul_mod(space - 1, multiplier, space) < space);
+        assert_ne!(mul_mod(1, multiplier, space), mul_mod(2, multiplier, space));
+    }
+
+    #[test]
+    fn shuffling_keeps_every_character() {
+        let mut alphabet = chars("0123456789abcdef");
+        consistent_shuffle(&mut alphabet, &chars("urlessen"));
+
+        assert_ne!(alphabet, chars("0123456789abcdef"));
+        alphabet.sort();
+        assert_eq!(alphabet, chars("0123456789abcdef"));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let config = |length: usize, alphabet: &str| Config {
+            short_url_length: length,
+            short_url_alphabet: String::from(alphabet),
+            ..Config::default()
+        };
+
+        assert!(from_config(&config(0, "ab")).is_err());
+        assert!(from_config(&config(17, "ab")).is_err());
+        assert!(from_config(&config(4, "a")).is_err());
+        assert!(from_config(&config(4, "aba")).is_err());
+        assert!(from_config(&config(4, "ab c")).is_err());
+        assert!(from_config(&config(16, "ab")).is_ok());
+    }
+}
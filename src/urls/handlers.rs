@@ -1,14 +1,54 @@
-use super::{CreateBody, PatchBody, Url};
-use crate::{auth::AuthenticatedUser, db::Db, urls::repo, Validate};
-use nanoid::nanoid;
-use rocket::{http::Status, serde::json::Json};
+use super::{
+    codes::{CodeError, CodeGenerator},
+    import, pages, qr,
+    validators::is_valid_alias,
+    BulkMode, BulkOptions, BulkReport, BulkResult, Click, CreateBody, CsvRow, Cursor, ExportError,
+    ExportFormat, ExportRow, IfNoneMatch, ImportQuery, ImportSource, ListQuery, ListScope,
+    LiveClick, PatchBody, Preview, PreviewCode, QrFormat, QrImage, QrQuery, Revision, SearchQuery,
+    Stats, StatsDimension, StatsQuery, StatsScope, Transfer, TransferBody, TransferStatus,
+    UnlockBody, Url, UrlPage, Visibility, Visit, VisitError, Visitor,
+};
+use crate::{
+    auth::{
+        passwords,
+        scopes::{StatsRead, UrlsRead, UrlsWrite},
+        InsufficientScope, Scope, Scoped,
+    },
+    config::Config,
+    db::Db,
+    error::{ApiError, ErrorBody},
+    events::{self, Audience, Events, LastEventId, Payload},
+    urls::repo,
+    webhooks,
+    workspaces::{self, Role},
+    Validate,
+};
+use chrono::{TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use rocket::{
+    data::{Data, ToByteUnit},
+    form::{self, error::ErrorKind, Form},
+    futures::StreamExt,
+    http::{Accept, ContentType, Cookie, CookieJar, SameSite, Status},
+    response::{
+        status::Custom,
+        stream::{EventStream, TextStream},
+        Redirect,
+    },
+    serde::json::{self, Json},
+    Shutdown, State,
+};
 use rocket_db_pools::Connection;
-use sqlx::types::Uuid;
+use rocket_ws::{Channel, WebSocket};
+use sqlx::{
+    types::{chrono::NaiveDateTime, Uuid},
+    Connection as _, PgConnection,
+};
 
 #[rocket::get("/<id>")]
 pub async fn get_url(
     mut db: Connection<Db>,
-    _user: AuthenticatedUser,
+    user: Scoped<UrlsRead>,
     id: Uuid,
 ) -> Result<Json<Url>, Status> {
     let url = repo::get_url(&mut db, id)
@@ -16,98 +56,1463 @@ pub async fn get_url(
         .or(Err(Status::InternalServerError))?
         .ok_or(Status::NotFound)?;
 
+    if role_of(&mut db, &url, user.id).await?.is_none() && !is_visible(&mut db, &url).await? {
+        return Err(Status::NotFound);
+    }
+
     Ok(Json(url))
 }
 
-#[rocket::get("/<username>/urls")]
-pub async fn get_urls_by_username(
+#[rocket::get("/<id>/clicks?<limit>")]
+pub async fn get_url_clicks(
     mut db: Connection<Db>,
-    _user: AuthenticatedUser,
-    username: &str,
+    user: Scoped<StatsRead>,
+    id: Uuid,
+    limit: Option<u16>,
+) -> Result<Json<Vec<Click>>, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Viewer).await?;
+
+    let limit = limit.unwrap_or(100).min(1000);
+    let clicks = repo::get_clicks_by_url(&mut db, id, limit.into())
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(clicks))
+}
+
+#[rocket::get("/<id>/stats?<query..>")]
+pub async fn get_url_stats(
+    mut db: Connection<Db>,
+    user: Scoped<StatsRead>,
+    id: Uuid,
+    query: StatsQuery,
+) -> Result<Json<Stats>, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Viewer).await?;
+
+    stats_of(&mut db, StatsScope::Url(id), query).await
+}
+
+#[rocket::get("/<id>/live")]
+pub async fn get_url_live(
+    mut db: Connection<Db>,
+    user: Scoped<StatsRead>,
+    id: Uuid,
+    ws: WebSocket,
+    events: &State<Events>,
+    shutdown: Shutdown,
+) -> Result<Channel<'static>, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Viewer).await?;
+
+    Ok(events::channel(
+        ws,
+        events.subscribe(),
+        Audience::Url(id),
+        shutdown,
+    ))
+}
+
+#[rocket::get("/<id>/revisions?<limit>")]
+pub async fn get_url_revisions(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    id: Uuid,
+    limit: Option<u16>,
+) -> Result<Json<Vec<Revision>>, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Viewer).await?;
+
+    let limit = limit.unwrap_or(100).min(1000);
+    let revisions = repo::get_revisions_by_url(&mut db, id, limit.into())
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(revisions))
+}
+
+#[rocket::get("/<id>/qr?<query..>")]
+pub async fn get_url_qr(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    id: Uuid,
+    query: QrQuery<'_>,
+    accept: Option<&Accept>,
+    if_none_match: IfNoneMatch<'_>,
+    config: &State<Config>,
+) -> Result<QrImage, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if url.visibility == Visibility::Private && role_of(&mut db, &url, user.id).await?.is_none() {
+        return Err(Status::NotFound);
+    }
+
+    qr_of(&url, &query, accept, if_none_match, config, "private")
+}
+
+#[rocket::post("/<id>/revisions/<revision_id>/restore")]
+pub async fn restore_url_revision(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+    revision_id: i64,
+    events: &State<Events>,
+) -> Result<Json<Url>, Status> {
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Editor).await?;
+
+    let revision = repo::get_revision(&mut db, id, revision_id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if revision.new_long_url == url.long_url {
+        return Ok(Json(url));
+    }
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    repo::insert_revision(
+        &mut tx,
+        id,
+        user.id,
+        Some(&url.long_url),
+        &revision.new_long_url,
+    )
+    .await
+    .or(Err(Status::InternalServerError))?;
+
+    let url = repo::set_long_url(&mut tx, id, &revision.new_long_url)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let event = url.event(Payload::LinkUpdated(url.clone()));
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
+
+    Ok(Json(url))
+}
+
+#[rocket::get("/search?<query..>")]
+pub async fn search_urls(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    query: SearchQuery,
 ) -> Result<Json<Vec<Url>>, Status> {
-    let urls = repo::get_urls_by_username(&mut db, &username)
+    let creator = (query.all != Some(true)).then_some(user.id);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let urls = repo::search_urls(&mut db, &query, creator, user.id, limit.into())
         .await
         .or(Err(Status::InternalServerError))?;
 
     Ok(Json(urls))
 }
 
+#[rocket::get("/<username>/urls?<query..>")]
+pub async fn get_urls_by_username(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    username: &str,
+    query: ListQuery,
+) -> Result<Json<UrlPage>, Status> {
+    let scope = ListScope::User {
+        username,
+        viewer: user.id,
+    };
+
+    list_urls(&mut db, scope, &query).await
+}
+
+#[rocket::get("/<id>/urls?<query..>")]
+pub async fn get_urls_by_workspace(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    id: Uuid,
+    query: ListQuery,
+) -> Result<Json<UrlPage>, Status> {
+    workspaces::require_role(&mut db, id, user.id, Role::Viewer).await?;
+
+    list_urls(&mut db, ListScope::Workspace(id), &query).await
+}
+
+#[rocket::get("/me/live")]
+pub async fn get_live(
+    mut db: Connection<Db>,
+    user: Scoped<StatsRead>,
+    ws: WebSocket,
+    events: &State<Events>,
+    shutdown: Shutdown,
+) -> Result<Channel<'static>, Status> {
+    let workspaces = workspaces::repo::get_workspace_ids_by_member(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    let audience = Audience::User {
+        id: user.id,
+        workspaces,
+        clicks: true,
+    };
+
+    Ok(events::channel(ws, events.subscribe(), audience, shutdown))
+}
+
+#[rocket::get("/me/events")]
+pub async fn get_events(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    last_event_id: LastEventId,
+    events: &State<Events>,
+    shutdown: Shutdown,
+) -> Result<EventStream![], Status> {
+    let workspaces = workspaces::repo::get_workspace_ids_by_member(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    let audience = Audience::User {
+        id: user.id,
+        workspaces,
+        clicks: user.scopes().contains(&Scope::StatsRead),
+    };
+
+    Ok(events::event_stream(
+        events,
+        last_event_id.0,
+        audience,
+        shutdown,
+    ))
+}
+
+#[rocket::get("/<username>/stats?<query..>")]
+pub async fn get_user_stats(
+    mut db: Connection<Db>,
+    user: Scoped<StatsRead>,
+    username: &str,
+    query: StatsQuery,
+) -> Result<Json<Stats>, Status> {
+    if username != user.username {
+        return Err(Status::Forbidden);
+    }
+
+    stats_of(&mut db, StatsScope::User(user.id), query).await
+}
+
+#[rocket::get("/<username>/urls/export?<format>&<clicks>")]
+pub async fn export_urls(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    username: &str,
+    format: form::Result<'_, ExportFormat>,
+    clicks: Option<bool>,
+    accept: Option<&Accept>,
+) -> Result<(ContentType, TextStream![String]), ExportError> {
+    if username != user.username {
+        return Err(Status::Forbidden.into());
+    }
+
+    let format = match format {
+        Ok(format) => format,
+        Err(e) if e.iter().all(|e| matches!(e.kind, ErrorKind::Missing)) => {
+            ExportFormat::negotiate(accept)
+        }
+        Err(_) => return Err(Status::UnprocessableEntity.into()),
+    };
+    let with_clicks = clicks.unwrap_or(false);
+
+    if with_clicks && !user.scopes().contains(&Scope::StatsRead) {
+        return Err(ExportError::Scope(InsufficientScope::new(Scope::StatsRead)));
+    }
+
+    let stream = TextStream! {
+        let mut rows = repo::export_urls(&mut db, user.id, with_clicks);
+        let mut first = true;
+
+        match format {
+            ExportFormat::Csv => yield csv_line(&ExportRow::csv_header(with_clicks)),
+            ExportFormat::Json => yield String::from("["),
+            ExportFormat::Ndjson => {}
+        }
+
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => {
+                    rocket::error!("failed to export links: {}", e);
+
+                    // Leave the JSON array unclosed, and end NDJSON with an
+                    // error line, so that a truncated export cannot be
+                    // mistaken for a complete one
+                    if format == ExportFormat::Ndjson {
+                        let error = ErrorBody {
+                            error: "export_failed",
+                            message: String::from("The export was interrupted"),
+                        };
+                        yield format!("{}\n", json::to_string(&error).unwrap_or_default());
+                    }
+
+                    return;
+                }
+            };
+
+            let json = || json::to_string(&row).unwrap_or_default();
+
+            match format {
+                ExportFormat::Csv => yield csv_line(&row.csv_record(with_clicks)),
+                ExportFormat::Json if first => yield json(),
+                ExportFormat::Json => yield format!(",{}", json()),
+                ExportFormat::Ndjson => yield format!("{}\n", json()),
+            }
+
+            first = false;
+        }
+
+        if format == ExportFormat::Json {
+            yield String::from("]");
+        }
+    };
+
+    Ok((format.content_type(), stream))
+}
+
 #[rocket::post("/", data = "<body>")]
 pub async fn create_url(
     mut db: Connection<Db>,
-    user: AuthenticatedUser,
+    user: Scoped<UrlsWrite>,
     body: Json<CreateBody>,
-) -> Result<Json<Url>, Status> {
-    if !body.validate() {
-        return Err(Status::UnprocessableEntity);
-    }
+    codes: &State<Box<dyn CodeGenerator>>,
+    events: &State<Events>,
+    config: &State<Config>,
+) -> Result<Json<Url>, ApiError> {
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+    let url = insert_new_url(&mut tx, user.id, &body, codes.as_ref(), config).await?;
+    let event = url.event(Payload::LinkCreated(url.clone()));
+
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
 
-    let url = repo::insert_url(
+    Ok(Json(url))
+}
+
+#[rocket::post("/bulk?<mode>", format = "json", data = "<body>", rank = 1)]
+pub async fn create_urls_json(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    mode: Option<BulkMode>,
+    body: Json<Vec<CreateBody>>,
+    codes: &State<Box<dyn CodeGenerator>>,
+    events: &State<Events>,
+    config: &State<Config>,
+) -> Result<Custom<Json<BulkReport>>, Status> {
+    let options = BulkOptions::new(mode.unwrap_or(BulkMode::Atomic));
+
+    create_urls(
         &mut db,
         user.id,
-        &body.title,
-        &body.description,
-        &body.long_url,
-        &nanoid!(8),
+        options,
+        body.into_inner().into_iter().map(Ok).collect(),
+        codes.as_ref(),
+        events,
+        config,
     )
     .await
-    .map_err(|e| match e.as_database_error() {
-        Some(e) if e.is_unique_violation() => Status::Conflict, // TODO: Implement collision prevention strategy
-        Some(e) if e.is_foreign_key_violation() => Status::NotFound,
-        _ => Status::InternalServerError,
-    })?;
+}
 
-    Ok(Json(url))
+#[rocket::post("/bulk?<mode>", format = "text/csv", data = "<body>", rank = 2)]
+pub async fn create_urls_csv(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    mode: Option<BulkMode>,
+    body: Data<'_>,
+    codes: &State<Box<dyn CodeGenerator>>,
+    events: &State<Events>,
+    config: &State<Config>,
+) -> Result<Custom<Json<BulkReport>>, Status> {
+    let csv = read_upload(body, config).await?;
+
+    let rows = csv::Reader::from_reader(csv.as_bytes())
+        .deserialize::<CsvRow>()
+        .map(|row| row.map(CreateBody::from).map_err(invalid_row))
+        .collect();
+
+    let options = BulkOptions::new(mode.unwrap_or(BulkMode::Atomic));
+
+    create_urls(
+        &mut db,
+        user.id,
+        options,
+        rows,
+        codes.as_ref(),
+        events,
+        config,
+    )
+    .await
+}
+
+#[rocket::post("/import?<query..>", data = "<body>")]
+pub async fn import_urls(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    query: ImportQuery,
+    body: Data<'_>,
+    codes: &State<Box<dyn CodeGenerator>>,
+    events: &State<Events>,
+    config: &State<Config>,
+) -> Result<Custom<Json<BulkReport>>, Status> {
+    let data = read_upload(body, config).await?;
+
+    let rows = match query.source {
+        ImportSource::Bookmarks => import::parse_bookmarks(&data).into_iter().map(Ok).collect(),
+        ImportSource::Csv => import::parse_csv(&data, &query)
+            .ok_or(Status::UnprocessableEntity)?
+            .into_iter()
+            .map(|row| row.map_err(invalid_row))
+            .collect(),
+    };
+
+    let options = BulkOptions {
+        mode: query.mode,
+        dry_run: query.dry_run,
+        alias_fallback: true,
+    };
+
+    create_urls(
+        &mut db,
+        user.id,
+        options,
+        rows,
+        codes.as_ref(),
+        events,
+        config,
+    )
+    .await
 }
 
 #[rocket::patch("/<id>", data = "<body>")]
 pub async fn patch_url(
     mut db: Connection<Db>,
-    user: AuthenticatedUser,
+    user: Scoped<UrlsWrite>,
     id: Uuid,
     body: Json<PatchBody>,
+    events: &State<Events>,
+    config: &State<Config>,
 ) -> Result<Json<Url>, Status> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity);
+    }
+
     let url = repo::get_url(&mut db, id)
         .await
         .or(Err(Status::InternalServerError))?
         .ok_or(Status::NotFound)?;
 
-    if url.creator != user.id {
-        return Err(Status::Forbidden);
+    authorize(&mut db, &url, user.id, Role::Editor).await?;
+
+    let password_hash = match &body.password {
+        Some(Some(p)) => Some(Some(
+            passwords::hash_password(&config.argon_secret, p).await?,
+        )),
+        Some(None) => Some(None),
+        None => None,
+    };
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    if let Some(tags) = &body.tags {
+        repo::set_url_tags(&mut tx, id, url.creator, tags)
+            .await
+            .or(Err(Status::InternalServerError))?;
+    }
+
+    if let Some(long_url) = body.long_url.as_deref().filter(|l| *l != url.long_url) {
+        repo::insert_revision(&mut tx, id, user.id, Some(&url.long_url), long_url)
+            .await
+            .or(Err(Status::InternalServerError))?;
     }
 
     let url = repo::patch_url(
-        &mut db,
+        &mut tx,
         id,
-        body.title.as_deref(),
-        body.description.as_deref(),
+        &body,
+        password_hash.as_ref().map(Option::as_deref),
     )
     .await
     .or(Err(Status::InternalServerError))?
     .ok_or(Status::NotFound)?;
 
+    let event = url.event(Payload::LinkUpdated(url.clone()));
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
+
     Ok(Json(url))
 }
 
 #[rocket::delete("/<id>")]
 pub async fn delete_url(
     mut db: Connection<Db>,
-    user: AuthenticatedUser,
+    user: Scoped<UrlsWrite>,
     id: Uuid,
+    events: &State<Events>,
 ) -> Result<Json<Url>, Status> {
     let url = repo::get_url(&mut db, id)
         .await
         .or(Err(Status::InternalServerError))?
         .ok_or(Status::NotFound)?;
 
-    if url.creator != user.id {
+    authorize(&mut db, &url, user.id, Role::Editor).await?;
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    let url = repo::delete_url(&mut tx, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let event = url.event(Payload::LinkDeleted { id });
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
+
+    Ok(Json(url))
+}
+
+#[rocket::get("/trash?<limit>")]
+pub async fn get_trash(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+    limit: Option<u16>,
+) -> Result<Json<Vec<Url>>, Status> {
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    let urls = repo::get_deleted_urls(&mut db, user.id, limit.into())
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(urls))
+}
+
+#[rocket::post("/<id>/restore")]
+pub async fn restore_url(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+    events: &State<Events>,
+) -> Result<Json<Url>, Status> {
+    let url = repo::get_deleted_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Editor).await?;
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    let url = repo::restore_url(&mut tx, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let event = url.event(Payload::LinkCreated(url.clone()));
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
+
+    Ok(Json(url))
+}
+
+#[rocket::post("/<id>/transfer", data = "<body>")]
+pub async fn transfer_url(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+    body: Json<TransferBody>,
+) -> Result<Json<Transfer>, ApiError> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    let url = repo::get_url(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    authorize(&mut db, &url, user.id, Role::Admin).await?;
+
+    let to_user = match &body.to_user {
+        Some(username) => Some(
+            workspaces::repo::get_user_id(&mut db, username)
+                .await
+                .or(Err(Status::InternalServerError))?
+                .ok_or(Status::NotFound)?,
+        ),
+        None => None,
+    };
+
+    let unchanged = match to_user {
+        Some(to_user) => url.workspace_id.is_none() && url.creator == to_user,
+        None => url.workspace_id == body.to_workspace,
+    };
+
+    if unchanged {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    let transfer = repo::insert_transfer(&mut db, id, user.id, to_user, body.to_workspace)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(e) if e.is_foreign_key_violation() => Status::NotFound,
+            _ => Status::InternalServerError,
+        })?
+        .ok_or_else(|| {
+            ApiError::new(
+                Status::Conflict,
+                "transfer_pending",
+                "This link already has a pending transfer",
+            )
+        })?;
+
+    Ok(Json(transfer))
+}
+
+#[rocket::get("/transfers")]
+pub async fn get_transfers(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsRead>,
+) -> Result<Json<Vec<Transfer>>, Status> {
+    let transfers = repo::get_pending_transfers(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(transfers))
+}
+
+#[rocket::post("/transfers/<id>/accept")]
+pub async fn accept_transfer(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+    events: &State<Events>,
+) -> Result<Json<Url>, Status> {
+    let transfer = repo::get_transfer(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if !is_recipient(&mut db, &transfer, user.id).await? {
         return Err(Status::Forbidden);
     }
 
-    let url = repo::delete_url(&mut db, id)
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    let transfer = repo::resolve_transfer(&mut tx, id, TransferStatus::Accepted)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::Conflict)?;
+
+    let url = repo::get_url(&mut tx, transfer.url_id)
         .await
         .or(Err(Status::InternalServerError))?
         .ok_or(Status::NotFound)?;
 
-    Ok(Json(url))
+    let creator = transfer.to_user.unwrap_or(url.creator);
+
+    if creator != url.creator && !url.tags.is_empty() {
+        repo::set_url_tags(&mut tx, url.id, creator, &url.tags)
+            .await
+            .or(Err(Status::InternalServerError))?;
+    }
+
+    let transferred = repo::transfer_url(&mut tx, url.id, creator, transfer.to_workspace)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    // The link leaves the sight of its previous owners and enters the sight of
+    // the new ones
+    let transfer_events = [
+        url.event(Payload::LinkDeleted { id: url.id }),
+        transferred.event(Payload::LinkCreated(transferred.clone())),
+    ];
+
+    for event in &transfer_events {
+        enqueue(&mut tx, event).await?;
+    }
+
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    transfer_events.into_iter().for_each(|e| events.publish(e));
+
+    Ok(Json(transferred))
+}
+
+#[rocket::post("/transfers/<id>/decline")]
+pub async fn decline_transfer(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+) -> Result<Json<Transfer>, Status> {
+    let transfer = repo::get_transfer(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if !is_recipient(&mut db, &transfer, user.id).await? {
+        return Err(Status::Forbidden);
+    }
+
+    let transfer = repo::resolve_transfer(&mut db, id, TransferStatus::Declined)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::Conflict)?;
+
+    Ok(Json(transfer))
+}
+
+#[rocket::delete("/transfers/<id>")]
+pub async fn cancel_transfer(
+    mut db: Connection<Db>,
+    user: Scoped<UrlsWrite>,
+    id: Uuid,
+) -> Result<Json<Transfer>, Status> {
+    let transfer = repo::get_transfer(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if transfer.from_user != user.id {
+        return Err(Status::Forbidden);
+    }
+
+    let transfer = repo::resolve_transfer(&mut db, id, TransferStatus::Cancelled)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::Conflict)?;
+
+    Ok(Json(transfer))
+}
+
+#[rocket::get("/<code>/qr?<query..>", rank = 3)]
+pub async fn qr_code(
+    mut db: Connection<Db>,
+    code: &str,
+    query: QrQuery<'_>,
+    accept: Option<&Accept>,
+    if_none_match: IfNoneMatch<'_>,
+    config: &State<Config>,
+) -> Result<QrImage, Status> {
+    let url = repo::get_url_by_short_url(&mut db, code)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if url.is_expired() {
+        return Err(Status::Gone);
+    }
+
+    // Shared caches must not keep codes of links that are not meant to be found
+    let cache = if url.visibility == Visibility::Public && url.password.is_none() {
+        "public"
+    } else {
+        "private"
+    };
+
+    qr_of(&url, &query, accept, if_none_match, config, cache)
+}
+
+#[rocket::get("/<code>", rank = 1)]
+pub async fn preview(
+    mut db: Connection<Db>,
+    code: PreviewCode<'_>,
+    cookies: &CookieJar<'_>,
+    accept: Option<&Accept>,
+) -> Result<Visit, VisitError> {
+    let url = repo::get_url_by_short_url(&mut db, code.0)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(VisitError::NotFound(pages::not_found()))?;
+
+    if url.is_expired() {
+        return Err(VisitError::Gone(pages::gone(url.fallback_url.as_deref())));
+    }
+
+    let show_destination = is_unlocked(cookies, &url);
+    let json = accept.is_some_and(|a| a.preferred().is_json());
+
+    Ok(preview_of(&mut db, url, show_destination, json).await?)
+}
+
+#[rocket::get("/<code>?<confirm>", rank = 2)]
+#[allow(clippy::too_many_arguments)]
+pub async fn redirect(
+    mut db: Connection<Db>,
+    code: &str,
+    confirm: Option<bool>,
+    visitor: Visitor,
+    cookies: &CookieJar<'_>,
+    accept: Option<&Accept>,
+    events: &State<Events>,
+    config: &State<Config>,
+) -> Result<Visit, VisitError> {
+    let url = repo::get_url_by_short_url(&mut db, code)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(VisitError::NotFound(pages::not_found()))?;
+
+    let gone = || VisitError::Gone(pages::gone(url.fallback_url.as_deref()));
+    let json = accept.is_some_and(|a| a.preferred().is_json());
+
+    if url.is_expired() {
+        return Err(gone());
+    }
+
+    if !is_unlocked(cookies, &url) {
+        return Err(password_challenge(&url, json, false));
+    }
+
+    if url.preview && confirm != Some(true) {
+        return Ok(preview_of(&mut db, url, true, json).await?);
+    }
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    let visited = repo::register_visit(&mut tx, url.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or_else(gone)?;
+
+    let click = repo::insert_click(&mut tx, visited.id, &visitor)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    let event = visited.event(Payload::Click(LiveClick::new(&visited, click)));
+    enqueue(&mut tx, &event).await?;
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    events.publish(event);
+
+    Ok(Visit::Redirect(
+        config.redirect_status.redirect(visited.long_url),
+    ))
+}
+
+#[rocket::post("/<code>", data = "<body>", format = "form")]
+pub async fn unlock(
+    mut db: Connection<Db>,
+    code: &str,
+    body: Form<UnlockBody>,
+    cookies: &CookieJar<'_>,
+    config: &State<Config>,
+) -> Result<Redirect, VisitError> {
+    unlock_url(&mut db, code, &body.password, cookies, config, false).await
+}
+
+#[rocket::post("/<code>", data = "<body>", format = "json")]
+pub async fn unlock_json(
+    mut db: Connection<Db>,
+    code: &str,
+    body: Json<UnlockBody>,
+    cookies: &CookieJar<'_>,
+    config: &State<Config>,
+) -> Result<Redirect, VisitError> {
+    unlock_url(&mut db, code, &body.password, cookies, config, true).await
+}
+
+async fn unlock_url(
+    db: &mut PgConnection,
+    code: &str,
+    password: &str,
+    cookies: &CookieJar<'_>,
+    config: &Config,
+    json: bool,
+) -> Result<Redirect, VisitError> {
+    let url = repo::get_url_by_short_url(db, code)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(VisitError::NotFound(pages::not_found()))?;
+
+    if url.is_expired() {
+        return Err(VisitError::Gone(pages::gone(url.fallback_url.as_deref())));
+    }
+
+    if let Some(password_hash) = &url.password {
+        match passwords::verify_password(&config.argon_secret, password, password_hash).await {
+            Ok(()) => {}
+            Err(status) if status == Status::Unauthorized => {
+                return Err(password_challenge(&url, json, true))
+            }
+            Err(status) => return Err(status.into()),
+        }
+
+        cookies.add_private(
+            Cookie::build((
+                unlock_cookie_name(&url),
+                unlock_token(password_hash).to_owned(),
+            ))
+            // Both the link and its preview, `/{code}+`, must see the cookie,
+            // and the latter is not under the path of the former
+            .path("/")
+            .max_age(rocket::time::Duration::seconds(
+                config.unlock_ttl_sec as i64,
+            ))
+            .same_site(SameSite::Lax),
+        );
+    }
+
+    Ok(Redirect::to(format!("/{}", url.short_url)))
+}
+
+async fn insert_new_url(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    body: &CreateBody,
+    codes: &dyn CodeGenerator,
+    config: &Config,
+) -> Result<Url, ApiError> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    if let Some(workspace_id) = body.workspace_id {
+        workspaces::require_role(db, workspace_id, user_id, Role::Editor).await?;
+    }
+
+    let password_hash = match &body.password {
+        Some(p) => Some(passwords::hash_password(&config.argon_secret, p).await?),
+        None => None,
+    };
+
+    let mut attempts = 0;
+
+    let url = loop {
+        attempts += 1;
+
+        let short_url = match &body.alias {
+            Some(alias) => alias.clone(),
+            None => codes.generate(db).await.map_err(|e| match e {
+                CodeError::Exhausted => ApiError::new(
+                    Status::ServiceUnavailable,
+                    "codes_exhausted",
+                    "No short codes are left, give the link an alias instead",
+                ),
+                CodeError::Database(_) => Status::InternalServerError.into(),
+            })?,
+        };
+
+        let url = repo::insert_url(db, user_id, body, &short_url, password_hash.as_deref())
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(e) if e.is_foreign_key_violation() => Status::NotFound,
+                _ => Status::InternalServerError,
+            })?;
+
+        match url {
+            Some(url) => break url,
+            None if body.alias.is_some() => {
+                return Err(ApiError::new(
+                    Status::Conflict,
+                    "alias_taken",
+                    format!("The alias \"{short_url}\" is already taken"),
+                ))
+            }
+            None if attempts < config.short_url_max_attempts => {}
+            None => return Err(Status::Conflict.into()),
+        }
+    };
+
+    repo::insert_revision(db, url.id, user_id, None, &url.long_url)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    match &body.tags {
+        Some(tags) => {
+            repo::set_url_tags(db, url.id, user_id, tags)
+                .await
+                .or(Err(Status::InternalServerError))?;
+
+            Ok(repo::get_url(db, url.id)
+                .await
+                .or(Err(Status::InternalServerError))?
+                .ok_or(Status::NotFound)?)
+        }
+        None => Ok(url),
+    }
+}
+
+async fn read_upload(body: Data<'_>, config: &Config) -> Result<String, Status> {
+    let data = body
+        .open(config.bulk_max_bytes.bytes())
+        .into_string()
+        .await
+        .or(Err(Status::BadRequest))?;
+
+    if !data.is_complete() {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    Ok(data.into_inner())
+}
+
+/// Drops the alias of an imported link when it is invalid or already in use,
+/// returning it so that it can be reported.
+async fn take_unavailable_alias(
+    db: &mut PgConnection,
+    body: &mut CreateBody,
+) -> Result<Option<String>, Status> {
+    let Some(alias) = &body.alias else {
+        return Ok(None);
+    };
+
+    let available = is_valid_alias(alias)
+        && !repo::short_url_exists(db, alias)
+            .await
+            .or(Err(Status::InternalServerError))?;
+
+    Ok(if available { None } else { body.alias.take() })
+}
+
+async fn create_urls(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    options: BulkOptions,
+    rows: Vec<Result<CreateBody, ApiError>>,
+    codes: &dyn CodeGenerator,
+    events: &Events,
+    config: &Config,
+) -> Result<Custom<Json<BulkReport>>, Status> {
+    if rows.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    if rows.len() > config.bulk_max_rows {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+    let mut results = Vec::with_capacity(rows.len());
+
+    for (row, body) in rows.into_iter().enumerate() {
+        let mut body = match body {
+            Ok(body) => body,
+            Err(e) => {
+                results.push(BulkResult::failed(row, e));
+                continue;
+            }
+        };
+
+        let mut savepoint = tx.begin().await.or(Err(Status::InternalServerError))?;
+
+        let alias_conflict = if options.alias_fallback {
+            take_unavailable_alias(&mut savepoint, &mut body).await?
+        } else {
+            None
+        };
+
+        let result = match insert_new_url(&mut savepoint, user_id, &body, codes, config).await {
+            Ok(url) => {
+                savepoint
+                    .commit()
+                    .await
+                    .or(Err(Status::InternalServerError))?;
+                BulkResult::created(row, url)
+            }
+            Err(e) => {
+                savepoint
+                    .rollback()
+                    .await
+                    .or(Err(Status::InternalServerError))?;
+                BulkResult::failed(row, e)
+            }
+        };
+
+        results.push(result.with_alias_conflict(alias_conflict));
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let accepted = failed == 0 || options.mode == BulkMode::BestEffort;
+    let committed = accepted && !options.dry_run;
+
+    if committed {
+        let created = results
+            .iter()
+            .filter_map(|r| r.url.as_ref())
+            .map(|url| url.event(Payload::LinkCreated(url.clone())))
+            .collect::<Vec<_>>();
+
+        for event in &created {
+            enqueue(&mut tx, event).await?;
+        }
+
+        tx.commit().await.or(Err(Status::InternalServerError))?;
+
+        created.into_iter().for_each(|e| events.publish(e));
+    } else {
+        tx.rollback().await.or(Err(Status::InternalServerError))?;
+    }
+
+    if options.dry_run {
+        results.iter_mut().for_each(BulkResult::discard);
+    } else if !committed {
+        results.iter_mut().for_each(BulkResult::roll_back);
+    }
+
+    let status = if accepted {
+        Status::Ok
+    } else {
+        Status::UnprocessableEntity
+    };
+
+    Ok(Custom(
+        status,
+        Json(BulkReport {
+            committed,
+            dry_run: options.dry_run,
+            created: if accepted { results.len() - failed } else { 0 },
+            failed,
+            results,
+        }),
+    ))
+}
+
+/// Reports a record of an uploaded CSV that could not be read, so that it fails
+/// on its own instead of rejecting the whole upload.
+fn invalid_row(error: csv::Error) -> ApiError {
+    ApiError::new(
+        Status::UnprocessableEntity,
+        "invalid_row",
+        error.to_string(),
+    )
+}
+
+fn csv_line<T: AsRef<[u8]>>(record: &[T]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(record).ok();
+
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+/// Queues the webhook deliveries of an event in the transaction of the change
+/// it describes, so that they are only sent if the change is committed.
+async fn enqueue(db: &mut PgConnection, event: &events::Event) -> Result<(), Status> {
+    webhooks::repo::insert_deliveries(db, event)
+        .await
+        .map(drop)
+        .or(Err(Status::InternalServerError))
+}
+
+async fn list_urls(
+    db: &mut PgConnection,
+    scope: ListScope<'_>,
+    query: &ListQuery,
+) -> Result<Json<UrlPage>, Status> {
+    let (sort, order) = (query.sort, query.order);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200) as usize;
+
+    let cursor = match &query.cursor {
+        Some(c) => Some(
+            Cursor::decode(c)
+                .filter(|c| c.sort == sort && c.order == order)
+                .ok_or(Status::UnprocessableEntity)?,
+        ),
+        None => None,
+    };
+
+    let mut urls = repo::get_urls(db, scope, query, cursor.as_ref(), limit as i64 + 1)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    let next = if urls.len() > limit {
+        urls.truncate(limit);
+        urls.last().map(|u| Cursor::after(u, sort, order).encode())
+    } else {
+        None
+    };
+
+    Ok(Json(UrlPage { urls, next }))
+}
+
+async fn role_of(db: &mut PgConnection, url: &Url, user_id: Uuid) -> Result<Option<Role>, Status> {
+    match url.workspace_id {
+        Some(workspace_id) => workspaces::repo::get_member_role(db, workspace_id, user_id)
+            .await
+            .or(Err(Status::InternalServerError)),
+        None => Ok((url.creator == user_id).then_some(Role::Owner)),
+    }
+}
+
+/// Whether anyone who knows the link may see its details, that is, it is not
+/// private and its creator does not have a private profile.
+async fn is_visible(db: &mut PgConnection, url: &Url) -> Result<bool, Status> {
+    if url.visibility == Visibility::Private {
+        return Ok(false);
+    }
+
+    repo::is_private_profile(db, url.creator)
+        .await
+        .map(|private| !private)
+        .or(Err(Status::InternalServerError))
+}
+
+async fn authorize(
+    db: &mut PgConnection,
+    url: &Url,
+    user_id: Uuid,
+    required: Role,
+) -> Result<(), Status> {
+    match role_of(db, url, user_id).await? {
+        Some(role) if role >= required => Ok(()),
+        _ => Err(Status::Forbidden),
+    }
+}
+
+async fn is_recipient(
+    db: &mut PgConnection,
+    transfer: &Transfer,
+    user_id: Uuid,
+) -> Result<bool, Status> {
+    match transfer.to_workspace {
+        Some(workspace_id) => Ok(workspaces::repo::get_member_role(db, workspace_id, user_id)
+            .await
+            .or(Err(Status::InternalServerError))?
+            .is_some_and(|role| role >= Role::Admin)),
+        None => Ok(transfer.to_user == Some(user_id)),
+    }
+}
+
+/// Gathers the click analytics of a scope. The range defaults to the last 30
+/// days, and is limited to 2000 buckets of the requested interval.
+async fn stats_of(
+    db: &mut PgConnection,
+    scope: StatsScope,
+    query: StatsQuery,
+) -> Result<Json<Stats>, Status> {
+    let tz = query.tz.0;
+    let local_to = query
+        .to
+        .map_or_else(|| Utc::now().with_timezone(&tz).naive_local(), |t| t.0);
+    let local_from = query.from.map_or(local_to - TimeDelta::days(30), |t| t.0);
+
+    let buckets = (local_to - local_from).num_seconds() / query.interval.duration().num_seconds();
+
+    if local_from >= local_to || buckets > 2000 {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let (from, to) = (utc_of(tz, local_from), utc_of(tz, local_to));
+    let limit = query.top.into();
+
+    let (clicks, unique_visitors) = repo::get_click_totals(db, scope, from, to)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    let series = repo::get_click_series(
+        db,
+        scope,
+        query.interval,
+        tz.name(),
+        from,
+        to,
+        local_from,
+        local_to,
+    )
+    .await
+    .or(Err(Status::InternalServerError))?;
+
+    let referrers = repo::get_top_clicks(db, scope, StatsDimension::Referrer, from, to, limit)
+        .await
+        .or(Err(Status::InternalServerError))?;
+    let countries = repo::get_top_clicks(db, scope, StatsDimension::Country, from, to, limit)
+        .await
+        .or(Err(Status::InternalServerError))?;
+    let browsers = repo::get_top_clicks(db, scope, StatsDimension::Browser, from, to, limit)
+        .await
+        .or(Err(Status::InternalServerError))?;
+    let operating_systems = repo::get_top_clicks(db, scope, StatsDimension::Os, from, to, limit)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(Stats {
+        interval: query.interval,
+        timezone: tz.name(),
+        from: local_from,
+        to: local_to,
+        clicks,
+        unique_visitors,
+        series,
+        referrers,
+        countries,
+        browsers,
+        operating_systems,
+    }))
+}
+
+/// Converts a local time to UTC with the offset in effect at that time, the
+/// earlier one when it is ambiguous. Local times skipped by a transition are
+/// converted with the offset in effect before it, found by stepping back out
+/// of the gap, which lasts a day at most.
+fn utc_of(tz: Tz, local: NaiveDateTime) -> NaiveDateTime {
+    (0..=24)
+        .map(TimeDelta::hours)
+        .find_map(|shift| {
+            tz.from_local_datetime(&(local - shift))
+                .earliest()
+                .map(|t| t.naive_utc() + shift)
+        })
+        .unwrap_or(local)
+}
+
+fn qr_of(
+    url: &Url,
+    query: &QrQuery<'_>,
+    accept: Option<&Accept>,
+    if_none_match: IfNoneMatch<'_>,
+    config: &Config,
+    visibility: &str,
+) -> Result<QrImage, Status> {
+    let format = match &query.format {
+        Ok(format) => *format,
+        Err(e) if e.iter().all(|e| matches!(e.kind, ErrorKind::Missing)) => {
+            QrFormat::negotiate(accept)
+        }
+        Err(_) => return Err(Status::UnprocessableEntity),
+    };
+
+    let short_url = format!(
+        "{}/{}",
+        config.public_url.trim_end_matches('/'),
+        url.short_url
+    );
+
+    qr::render(&short_url, url, format, query, if_none_match, visibility)
+}
+
+async fn preview_of(
+    db: &mut PgConnection,
+    url: Url,
+    show_destination: bool,
+    json: bool,
+) -> Result<Visit, Status> {
+    let preview = if is_visible(db, &url).await? {
+        let creator = repo::get_username(db, url.creator)
+            .await
+            .or(Err(Status::InternalServerError))?;
+
+        Preview::new(url, creator, show_destination)
+    } else {
+        Preview::limited(url, show_destination)
+    };
+
+    if json {
+        Ok(Visit::PreviewJson(Json(preview)))
+    } else {
+        Ok(Visit::PreviewPage(pages::preview(&preview)))
+    }
+}
+
+fn is_unlocked(cookies: &CookieJar<'_>, url: &Url) -> bool {
+    url.password.as_deref().is_none_or(|password_hash| {
+        cookies
+            .get_private(&unlock_cookie_name(url))
+            .is_some_and(|c| c.value() == unlock_token(password_hash))
+    })
+}
+
+fn unlock_cookie_name(url: &Url) -> String {
+    format!("unlock_{}", url.id)
+}
+
+// Ties the unlock cookie to the current password, so changing it locks the
+// link again for everyone.
+fn unlock_token(password_hash: &str) -> &str {
+    &password_hash[password_hash.len().saturating_sub(16)..]
+}
+
+fn password_challenge(url: &Url, json: bool, failed: bool) -> VisitError {
+    match (json, failed) {
+        (false, _) => VisitError::Locked(pages::password_challenge(&url.short_url, failed)),
+        (true, false) => VisitError::Api(ApiError::new(
+            Status::Unauthorized,
+            "password_required",
+            "This short link is protected by a password",
+        )),
+        (true, true) => VisitError::Api(ApiError::new(
+            Status::Unauthorized,
+            "invalid_password",
+            "The password for this short link is wrong",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, month: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn each_bound_gets_its_own_offset() {
+        let tz = Tz::Europe__Berlin;
+        let (from, to) = (utc_of(tz, at(30, 3, 0, 0)), utc_of(tz, at(1, 4, 0, 0)));
+
+        assert_eq!(from, at(29, 3, 23, 0));
+        assert_eq!(to, at(31, 3, 22, 0));
+        assert_eq!(to - from, TimeDelta::hours(47));
+    }
+
+    #[test]
+    fn skipped_times_keep_the_offset_before_the_gap() {
+        assert_eq!(
+            utc_of(Tz::Europe__Berlin, at(31, 3, 2, 30)),
+            at(31, 3, 1, 30)
+        );
+        assert_eq!(
+            utc_of(Tz::America__New_York, at(10, 3, 2, 30)),
+            at(10, 3, 7, 30)
+        );
+    }
+
+    #[test]
+    fn repeated_times_take_the_earlier_offset() {
+        assert_eq!(
+            utc_of(Tz::Europe__Berlin, at(27, 10, 2, 30)),
+            at(27, 10, 0, 30)
+        );
+    }
 }
@@ -0,0 +1,432 @@
+use super::{CreateBody, ImportPreset, ImportQuery};
+use crate::{tags::validators::is_valid_tag, utils::unescape_html};
+
+const TITLE_MAX_LEN: usize = 64;
+const DESCRIPTION_MAX_LEN: usize = 256;
+
+struct Columns<'a> {
+    long_url: &'a str,
+    title: Option<&'a str>,
+    description: Option<&'a str>,
+    alias: Option<&'a str>,
+    tags: Option<&'a str>,
+}
+
+impl ImportPreset {
+    fn columns(self) -> Columns<'static> {
+        match self {
+            ImportPreset::Generic => Columns {
+                long_url: "long_url",
+                title: Some("title"),
+                description: Some("description"),
+                alias: Some("alias"),
+                tags: Some("tags"),
+            },
+            ImportPreset::Urlessen => Columns {
+                long_url: "long_url",
+                title: Some("title"),
+                description: Some("description"),
+                alias: Some("short_url"),
+                tags: Some("tags"),
+            },
+            ImportPreset::Bitly => Columns {
+                long_url: "long_url",
+                title: Some("title"),
+                description: None,
+                alias: Some("link"),
+                tags: Some("tags"),
+            },
+            ImportPreset::Rebrandly => Columns {
+                long_url: "destination",
+                title: Some("title"),
+                description: None,
+                alias: Some("slashtag"),
+                tags: None,
+            },
+        }
+    }
+}
+
+/// Parses a Netscape bookmark file, as exported by browsers.
+///
+/// Each `<A>` element becomes a link, with its `SHORTCUTURL` as the alias and
+/// the `<DD>` that follows it as the description. The enclosing `<H3>` folder
+/// is kept as a tag when its name can be turned into a valid one.
+pub fn parse_bookmarks(html: &str) -> Vec<CreateBody> {
+    let lower = html.to_ascii_lowercase();
+    let mut bodies = Vec::new();
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut folder = None;
+    let mut pos = 0;
+
+    while let Some(start) = lower[pos..].find('<').map(|i| pos + i) {
+        let Some(end) = lower[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+
+        let element = &html[start + 1..end];
+        let name = lower[start + 1..end]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+
+        pos = end + 1;
+
+        match name {
+            "h3" => {
+                let (text, next) = text_until(html, &lower, pos, "</h3");
+                folder = Some(text);
+                pos = next;
+            }
+            "dl" => folders.push(folder.take()),
+            "/dl" => {
+                folders.pop();
+            }
+            "a" => {
+                let (title, next) = text_until(html, &lower, pos, "</a");
+                pos = next;
+
+                let Some(long_url) = attribute(element, "href") else {
+                    continue;
+                };
+
+                let rest = &lower[pos..];
+                let skipped = rest.len() - rest.trim_start().len();
+
+                let description = if rest.trim_start().starts_with("<dd>") {
+                    let from = pos + skipped + 4;
+                    let end = lower[from..].find('<').map_or(html.len(), |i| from + i);
+                    pos = end;
+                    unescape_html(html[from..end].trim())
+                } else {
+                    String::new()
+                };
+
+                let tags = folders
+                    .last()
+                    .cloned()
+                    .flatten()
+                    .and_then(|name| folder_tag(&name))
+                    .map(|tag| vec![tag]);
+
+                bodies.push(body(
+                    truncate(&title, TITLE_MAX_LEN),
+                    truncate(&description, DESCRIPTION_MAX_LEN),
+                    long_url,
+                    attribute(element, "shortcuturl"),
+                    tags,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    bodies
+}
+
+/// Parses a CSV export, mapping its columns according to the preset in the
+/// query and any column overrides given alongside it. Headers are matched
+/// case-insensitively, and `None` is returned if the link column is missing.
+/// Records that cannot be read are kept as errors, in place of their rows.
+pub fn parse_csv(data: &str, query: &ImportQuery) -> Option<Vec<Result<CreateBody, csv::Error>>> {
+    let preset = query.preset.columns();
+    let columns = Columns {
+        long_url: query.url_column.as_deref().unwrap_or(preset.long_url),
+        title: query.title_column.as_deref().or(preset.title),
+        description: query.description_column.as_deref().or(preset.description),
+        alias: query.alias_column.as_deref().or(preset.alias),
+        tags: query.tags_column.as_deref().or(preset.tags),
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+
+    let headers = reader.headers().ok()?.clone();
+    let index = |column: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(column));
+
+    let long_url = index(columns.long_url)?;
+    let title = columns.title.and_then(index);
+    let description = columns.description.and_then(index);
+    let alias = columns.alias.and_then(index);
+    let tags = columns.tags.and_then(index);
+
+    let mut bodies = Vec::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                bodies.push(Err(e));
+                continue;
+            }
+        };
+
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+
+        let alias = field(alias)
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .filter(|a| !a.is_empty())
+            .map(str::to_string);
+
+        let tags = Some(
+            field(tags)
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .filter(|t| is_valid_tag(t))
+                .collect::<Vec<_>>(),
+        )
+        .filter(|t| !t.is_empty());
+
+        bodies.push(Ok(body(
+            truncate(field(title), TITLE_MAX_LEN),
+            truncate(field(description), DESCRIPTION_MAX_LEN),
+            field(Some(long_url)).to_string(),
+            alias,
+            tags,
+        )));
+    }
+
+    Some(bodies)
+}
+
+fn body(
+    title: String,
+    description: String,
+    long_url: String,
+    alias: Option<String>,
+    tags: Option<Vec<String>>,
+) -> CreateBody {
+    CreateBody {
+        title,
+        description,
+        long_url,
+        alias,
+        workspace_id: None,
+        expires_at: None,
+        max_visits: None,
+        fallback_url: None,
+        password: None,
+        preview: None,
+        visibility: None,
+        tags,
+    }
+}
+
+/// Returns the unescaped text from `pos` up to the next occurrence of
+/// `closing`, along with the position right after the closing tag.
+fn text_until(html: &str, lower: &str, pos: usize, closing: &str) -> (String, usize) {
+    match lower[pos..].find(closing).map(|i| pos + i) {
+        Some(end) => {
+            let next = lower[end..].find('>').map_or(html.len(), |i| end + i + 1);
+            (unescape_html(html[pos..end].trim()), next)
+        }
+        None => (unescape_html(html[pos..].trim()), html.len()),
+    }
+}
+
+/// Looks up an attribute of an element, given its contents between the angle
+/// brackets. Values may be double quoted, single quoted or bare.
+fn attribute(element: &str, name: &str) -> Option<String> {
+    let mut rest = element.split_once(char::is_whitespace)?.1;
+
+    loop {
+        rest = rest.trim_start();
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = after[1..].find(q).map_or(after.len(), |i| i + 1);
+                        (&after[1..end], after.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+                };
+                rest = remaining;
+                Some(value)
+            }
+            None => None,
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return value
+                .map(|v| unescape_html(v.trim()))
+                .filter(|v| !v.is_empty());
+        }
+    }
+}
+
+/// Turns a folder name into a tag, e.g. "Bookmarks Bar" into "bookmarks-bar".
+fn folder_tag(name: &str) -> Option<String> {
+    let tag = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+
+    Some(tag).filter(|t| is_valid_tag(t))
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    let mut end = text.len().min(max_len);
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    text[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urls::{BulkMode, ImportSource};
+
+    fn query(preset: ImportPreset) -> ImportQuery {
+        ImportQuery {
+            source: ImportSource::Csv,
+            preset,
+            url_column: None,
+            title_column: None,
+            description_column: None,
+            alias_column: None,
+            tags_column: None,
+            dry_run: false,
+            mode: BulkMode::BestEffort,
+        }
+    }
+
+    #[test]
+    fn bookmarks_are_tagged_with_their_innermost_folder() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Work Stuff</H3>
+    <DL><p>
+        <DT><H3>Deep</H3>
+        <DL><p>
+            <DT><A HREF="https://deep.example.com" SHORTCUTURL=deep>Deep &amp; far</A>
+            <DD>Somewhere &lt;down&gt; there
+        </DL><p>
+        <DT><A HREF='https://work.example.com'>Work</A>
+    </DL><p>
+    <DT><A HREF="https://top.example.com">Top</A>
+</DL>
+"#;
+
+        let bodies = parse_bookmarks(html);
+        let summary = bodies
+            .iter()
+            .map(|b| {
+                (
+                    b.title.as_str(),
+                    b.description.as_str(),
+                    b.long_url.as_str(),
+                    b.alias.as_deref(),
+                    b.tags.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            [
+                (
+                    "Deep & far",
+                    "Somewhere <down> there",
+                    "https://deep.example.com",
+                    Some("deep"),
+                    Some(vec![String::from("deep")]),
+                ),
+                (
+                    "Work",
+                    "",
+                    "https://work.example.com",
+                    None,
+                    Some(vec![String::from("work-stuff")]),
+                ),
+                ("Top", "", "https://top.example.com", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn bookmarks_without_href_are_skipped() {
+        let bodies = parse_bookmarks(r#"<DL><DT><A NAME="x">No link</A></DL>"#);
+        assert!(bodies.is_empty());
+    }
+
+    #[test]
+    fn attributes_may_be_quoted_or_bare() {
+        let element =
+            r#"A HREF="https://example.com/?a=1&amp;b=2" ICON='data:x' SHORTCUTURL=ex ADD_DATE"#;
+
+        assert_eq!(
+            attribute(element, "href").as_deref(),
+            Some("https://example.com/?a=1&b=2")
+        );
+        assert_eq!(attribute(element, "icon").as_deref(), Some("data:x"));
+        assert_eq!(attribute(element, "shortcuturl").as_deref(), Some("ex"));
+        assert_eq!(attribute(element, "add_date"), None);
+        assert_eq!(attribute(element, "missing"), None);
+    }
+
+    #[test]
+    fn truncation_keeps_whole_characters() {
+        assert_eq!(truncate("ação", 3), "a\u{e7}");
+        assert_eq!(truncate("日本語", 4), "日");
+        assert_eq!(truncate("short", 64), "short");
+    }
+
+    #[test]
+    fn bitly_links_are_reduced_to_their_alias() {
+        let data = "Title,Long_URL,Link,Tags\nDocs,https://example.com/docs,https://bit.ly/abc123/,rust web\n";
+        let bodies = parse_csv(data, &query(ImportPreset::Bitly)).unwrap();
+        let body = bodies[0].as_ref().unwrap();
+
+        assert_eq!(body.title, "Docs");
+        assert_eq!(body.long_url, "https://example.com/docs");
+        assert_eq!(body.alias.as_deref(), Some("abc123"));
+        assert_eq!(
+            body.tags,
+            Some(vec![String::from("rust"), String::from("web")])
+        );
+    }
+
+    #[test]
+    fn csv_tags_are_lowercased_and_invalid_ones_dropped() {
+        let data = "long_url,tags\nhttps://example.com/a,Rust  WEB-dev c++ naïve\nhttps://example.com/b,C++\n";
+        let bodies = parse_csv(data, &query(ImportPreset::Generic)).unwrap();
+        let tags = bodies
+            .iter()
+            .map(|b| b.as_ref().unwrap().tags.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tags,
+            [
+                Some(vec![String::from("rust"), String::from("web-dev")]),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_link_column_is_rejected() {
+        let data = "title,url\nDocs,https://example.com/docs\n";
+        assert!(parse_csv(data, &query(ImportPreset::Generic)).is_none());
+    }
+}
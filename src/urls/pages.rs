@@ -0,0 +1,130 @@
+use super::{validators::is_valid_long_url, Preview};
+use crate::utils::escape_html;
+use rocket::response::content::RawHtml;
+
+pub fn not_found() -> RawHtml<&'static str> {
+    RawHtml(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Link not found</title>
+</head>
+<body>
+    <h1>Link not found</h1>
+    <p>This short link does not exist or has been removed.</p>
+</body>
+</html>
+"#,
+    )
+}
+
+pub fn gone(fallback_url: Option<&str>) -> RawHtml<String> {
+    let fallback = fallback_url
+        .filter(|u| is_valid_long_url(u))
+        .map(|u| {
+            let u = escape_html(u);
+            format!("\n    <p>You may want to visit <a href=\"{u}\">{u}</a> instead.</p>")
+        })
+        .unwrap_or_default();
+
+    RawHtml(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Link expired</title>
+</head>
+<body>
+    <h1>Link expired</h1>
+    <p>This short link has expired or reached its visit limit.</p>{fallback}
+</body>
+</html>
+"#
+    ))
+}
+
+pub fn password_challenge(code: &str, failed: bool) -> RawHtml<String> {
+    let code = escape_html(code);
+    let error = if failed {
+        "\n    <p>Wrong password, please try again.</p>"
+    } else {
+        ""
+    };
+
+    RawHtml(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Password required</title>
+</head>
+<body>
+    <h1>Password required</h1>
+    <p>This short link is protected. Enter its password to continue.</p>{error}
+    <form method="post" action="/{code}">
+        <input type="password" name="password" required autofocus>
+        <button type="submit">Continue</button>
+    </form>
+</body>
+</html>
+"#
+    ))
+}
+
+pub fn preview(preview: &Preview) -> RawHtml<String> {
+    let code = escape_html(&preview.short_url);
+    let destination = match (&preview.long_url, &preview.host) {
+        (Some(u), _) => {
+            let u = escape_html(u);
+            format!("<a href=\"/{code}?confirm=true\">{u}</a>")
+        }
+        (None, Some(host)) => {
+            let host = escape_html(host);
+            format!("<a href=\"/{code}?confirm=true\">{host}</a>")
+        }
+        (None, None) => {
+            format!("<a href=\"/{code}\">hidden, this link is protected by a password</a>")
+        }
+    };
+
+    let (heading, details) = match &preview.details {
+        Some(details) => {
+            let title = escape_html(&details.title);
+            let description = escape_html(&details.description);
+            let creator = escape_html(&details.creator);
+            let created_at = details.created_at.format("%Y-%m-%d");
+            let times_visited = details.times_visited;
+
+            (
+                format!("<h1>{title}</h1>\n    <p>{description}</p>"),
+                format!(
+                    r#"
+        <dt>Created by</dt>
+        <dd>{creator}, on {created_at}</dd>
+        <dt>Visits</dt>
+        <dd>{times_visited}</dd>"#
+                ),
+            )
+        }
+        None => (format!("<h1>/{code}</h1>"), String::new()),
+    };
+
+    RawHtml(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Preview of /{code}</title>
+</head>
+<body>
+    {heading}
+    <dl>
+        <dt>Destination</dt>
+        <dd>{destination}</dd>{details}
+    </dl>
+</body>
+</html>
+"#
+    ))
+}
@@ -0,0 +1,189 @@
+use super::{IfNoneMatch, QrColor, QrFormat, QrImage, QrLevel, QrQuery, Url};
+use qrcode::{Color, EcLevel, QrCode};
+use rocket::http::{Header, Status};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+const MIN_MAX_AGE_SEC: i64 = 60;
+const MAX_MAX_AGE_SEC: i64 = 86400;
+
+impl QrLevel {
+    fn ec_level(self) -> EcLevel {
+        match self {
+            QrLevel::L => EcLevel::L,
+            QrLevel::M => EcLevel::M,
+            QrLevel::Q => EcLevel::Q,
+            QrLevel::H => EcLevel::H,
+        }
+    }
+}
+
+impl QrColor {
+    fn svg_fill(self) -> String {
+        let [r, g, b, a] = self.0;
+        let mut fill = format!("fill=\"#{r:02x}{g:02x}{b:02x}\"");
+
+        if a != 255 {
+            write!(fill, " fill-opacity=\"{:.3}\"", a as f32 / 255.0).ok();
+        }
+
+        fill
+    }
+}
+
+struct Modules {
+    width: u32,
+    margin: u32,
+    colors: Vec<Color>,
+}
+
+impl Modules {
+    fn new(code: &QrCode, margin: u32) -> Self {
+        Modules {
+            width: code.width() as u32,
+            margin,
+            colors: code.to_colors(),
+        }
+    }
+
+    /// Side of the code in modules, including the quiet zone around it.
+    fn side(&self) -> u32 {
+        self.width + self.margin * 2
+    }
+
+    fn is_dark(&self, x: u32, y: u32) -> bool {
+        match (x.checked_sub(self.margin), y.checked_sub(self.margin)) {
+            (Some(x), Some(y)) if x < self.width && y < self.width => {
+                self.colors[(y * self.width + x) as usize] == Color::Dark
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Renders `data` as a QR code, answering with `304 Not Modified` when the
+/// client already holds the same image.
+///
+/// The image is cached for a tenth of the time elapsed since the link was last
+/// updated, so recently edited links are revalidated sooner.
+pub fn render(
+    data: &str,
+    url: &Url,
+    format: QrFormat,
+    query: &QrQuery<'_>,
+    if_none_match: IfNoneMatch<'_>,
+    visibility: &str,
+) -> Result<QrImage, Status> {
+    let etag = etag(data, url, format, query);
+    let age = (chrono::Utc::now().naive_utc() - url.updated_at).num_seconds() / 10;
+    let max_age = age.clamp(MIN_MAX_AGE_SEC, MAX_MAX_AGE_SEC);
+
+    let not_modified = if_none_match
+        .0
+        .is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag || t.trim() == "*"));
+
+    let body = if not_modified {
+        (Status::NotModified, Vec::new())
+    } else {
+        let code = QrCode::with_error_correction_level(data, query.ec.ec_level())
+            .or(Err(Status::UnprocessableEntity))?;
+        let modules = Modules::new(&code, query.margin);
+
+        let image = match format {
+            QrFormat::Png => png(&modules, query).or(Err(Status::InternalServerError))?,
+            QrFormat::Svg => svg(&modules, query).into_bytes(),
+        };
+
+        (Status::Ok, image)
+    };
+
+    Ok(QrImage {
+        body,
+        content_type: format.content_type(),
+        cache_control: Header::new("Cache-Control", format!("{visibility}, max-age={max_age}")),
+        etag: Header::new("ETag", etag),
+        last_modified: Header::new(
+            "Last-Modified",
+            url.updated_at
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        ),
+    })
+}
+
+fn etag(data: &str, url: &Url, format: QrFormat, query: &QrQuery<'_>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_bytes());
+    hasher.update(url.updated_at.and_utc().timestamp_micros().to_be_bytes());
+    hasher.update([format as u8, query.ec as u8]);
+    hasher.update(query.size.to_be_bytes());
+    hasher.update(query.margin.to_be_bytes());
+    hasher.update(query.fg.0);
+    hasher.update(query.bg.0);
+
+    format!("\"{}\"", hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Renders the modules with the largest whole number of pixels per module that
+/// fits in the requested size, using at least one pixel per module.
+fn png(modules: &Modules, query: &QrQuery<'_>) -> Result<Vec<u8>, png::EncodingError> {
+    let scale = (query.size / modules.side()).max(1);
+    let side = modules.side() * scale;
+    let mut pixels = Vec::with_capacity((side * side * 4) as usize);
+
+    for y in 0..side {
+        for x in 0..side {
+            let color = if modules.is_dark(x / scale, y / scale) {
+                query.fg
+            } else {
+                query.bg
+            };
+
+            pixels.extend_from_slice(&color.0);
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, side, side);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+
+    Ok(image)
+}
+
+fn svg(modules: &Modules, query: &QrQuery<'_>) -> String {
+    let side = modules.side();
+    let mut path = String::new();
+
+    for y in 0..side {
+        let mut x = 0;
+
+        while x < side {
+            if !modules.is_dark(x, y) {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+
+            while x < side && modules.is_dark(x, y) {
+                x += 1;
+            }
+
+            write!(path, "M{start} {y}h{}v1h-{}z", x - start, x - start).ok();
+        }
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+         viewBox=\"0 0 {side} {side}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{side}\" height=\"{side}\" {bg}/><path d=\"{path}\" {fg}/></svg>",
+        size = query.size,
+        bg = query.bg.svg_fill(),
+        fg = query.fg.svg_fill(),
+    )
+}
@@ -1,56 +1,190 @@
-use super::Url;
-use sqlx::{types::Uuid, PgConnection};
+use super::{
+    tag_requirement, Click, CreateBody, Cursor, ExportRow, ListQuery, ListScope, PatchBody,
+    Revision, SearchQuery, SortKey, SortOrder, StatsBucket, StatsDimension, StatsEntry,
+    StatsInterval, StatsScope, Transfer, TransferStatus, Url, Visibility, Visitor,
+};
+use rocket::futures::stream::BoxStream;
+use sqlx::{
+    postgres::PgQueryResult,
+    types::{chrono::NaiveDateTime, Uuid},
+    PgConnection, Postgres, QueryBuilder,
+};
 
 pub async fn get_url(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
-    sqlx::query_as!(Url, "SELECT * FROM urls WHERE id = $1;", id,)
-        .fetch_optional(&mut *db)
-        .await
-}
-
-pub async fn get_urls_by_username(
-    db: &mut PgConnection,
-    username: &str,
-) -> Result<Vec<Url>, sqlx::Error> {
     sqlx::query_as!(
         Url,
         r#"
-        SELECT urls.*
+        SELECT
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!"
         FROM urls
-        JOIN users ON users.id = urls.creator
-        WHERE users.username = $1;
+        WHERE id = $1 AND deleted_at IS NULL;
         "#,
-        username,
+        id,
     )
-    .fetch_all(&mut *db)
+    .fetch_optional(&mut *db)
     .await
 }
 
+pub async fn get_urls(
+    db: &mut PgConnection,
+    scope: ListScope<'_>,
+    query: &ListQuery,
+    cursor: Option<&Cursor>,
+    limit: i64,
+) -> Result<Vec<Url>, sqlx::Error> {
+    let (column, cast) = match query.sort {
+        SortKey::CreatedAt => ("urls.created_at", "timestamp"),
+        SortKey::UpdatedAt => ("urls.updated_at", "timestamp"),
+        SortKey::TimesVisited => ("urls.times_visited", "integer"),
+        SortKey::Title => ("urls.title", "text"),
+    };
+    let (direction, comparison) = match query.order {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    };
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT urls.*, url_tag_names(urls.id) AS tags
+        FROM urls
+        JOIN users ON users.id = urls.creator
+        WHERE urls.deleted_at IS NULL"#,
+    );
+
+    match scope {
+        ListScope::User { username, viewer } => {
+            builder
+                .push(" AND users.username = ")
+                .push_bind(username)
+                .push(" AND ((urls.workspace_id IS NULL AND users.id = ")
+                .push_bind(viewer)
+                .push(
+                    r#")
+                    OR urls.workspace_id IN (
+                        SELECT workspace_id FROM workspace_members WHERE user_id = "#,
+                )
+                .push_bind(viewer)
+                .push(") OR (NOT users.private_profile AND urls.visibility = 'public'))");
+        }
+        ListScope::Workspace(workspace_id) => {
+            builder
+                .push(" AND urls.workspace_id = ")
+                .push_bind(workspace_id);
+        }
+    }
+
+    if let Some(from) = &query.from {
+        builder.push(" AND urls.created_at >= ").push_bind(from.0);
+    }
+
+    if let Some(to) = &query.to {
+        builder.push(" AND urls.created_at < ").push_bind(to.0);
+    }
+
+    if let Some(q) = &query.q {
+        builder
+            .push(" AND (strpos(lower(urls.title), lower(")
+            .push_bind(q)
+            .push(")) > 0 OR strpos(lower(urls.description), lower(")
+            .push_bind(q)
+            .push(")) > 0)");
+    }
+
+    if !query.tags.is_empty() {
+        let (tags, min_matches) = tag_requirement(&query.tags, query.tag_match);
+
+        builder
+            .push(
+                r#"
+                AND (
+                    SELECT count(DISTINCT tags.name)
+                    FROM url_tags
+                    JOIN tags ON tags.id = url_tags.tag_id
+                    WHERE url_tags.url_id = urls.id AND tags.name = ANY("#,
+            )
+            .push_bind(tags)
+            .push(")) >= ")
+            .push_bind(min_matches);
+    }
+
+    if let Some(cursor) = cursor {
+        builder
+            .push(format_args!(" AND ({column}, urls.id) {comparison} ("))
+            .push_bind(&cursor.value)
+            .push(format_args!("::{cast}, "))
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    builder
+        .push(format_args!(
+            " ORDER BY {column} {direction}, urls.id {direction} LIMIT "
+        ))
+        .push_bind(limit);
+
+    builder.build_query_as::<Url>().fetch_all(&mut *db).await
+}
+
 pub async fn insert_url(
     db: &mut PgConnection,
     creator: Uuid,
-    title: &str,
-    description: &str,
-    long_url: &str,
+    body: &CreateBody,
     short_url: &str,
-) -> Result<Url, sqlx::Error> {
+    password_hash: Option<&str>,
+) -> Result<Option<Url>, sqlx::Error> {
     sqlx::query_as!(
         Url,
         r#"
         INSERT INTO urls (
             creator,
+            workspace_id,
             title,
             description,
             long_url,
-            short_url
+            short_url,
+            expires_at,
+            max_visits,
+            fallback_url,
+            password,
+            preview,
+            visibility
         )
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING *;
+        VALUES (
+            $1, $12, $2, $3, $4, $5, $6, $7, $8, $9,
+            COALESCE($10, false),
+            COALESCE($11::url_visibility, 'private')
+        )
+        ON CONFLICT (short_url) DO NOTHING
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
         "#,
         creator,
-        title,
-        description,
-        long_url,
-        short_url
+        body.title,
+        body.description,
+        body.long_url,
+        short_url,
+        body.expires_at,
+        body.max_visits,
+        body.fallback_url,
+        password_hash,
+        body.preview,
+        body.visibility as Option<Visibility>,
+        body.workspace_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn short_url_exists(db: &mut PgConnection, short_url: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM urls WHERE short_url = $1) AS "exists!";"#,
+        short_url,
     )
     .fetch_one(&mut *db)
     .await
@@ -59,66 +193,789 @@ pub async fn insert_url(
 pub async fn patch_url(
     db: &mut PgConnection,
     id: Uuid,
-    title: Option<&str>,
-    description: Option<&str>,
+    body: &PatchBody,
+    password_hash: Option<Option<&str>>,
 ) -> Result<Option<Url>, sqlx::Error> {
-    match (title, description) {
-        (None, None) => get_url(db, id).await,
-        (None, Some(description)) => {
-            sqlx::query_as!(
-                Url,
-                r#"
-                UPDATE urls SET
-                    description = $2,
-                    updated_at = NOW()
-                WHERE id = $1
-                RETURNING *;
-                "#,
-                id,
-                description
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            title = COALESCE($2, title),
+            description = COALESCE($3, description),
+            long_url = COALESCE($13, long_url),
+            expires_at = CASE WHEN $4 THEN $5 ELSE expires_at END,
+            max_visits = CASE WHEN $6 THEN $7 ELSE max_visits END,
+            fallback_url = CASE WHEN $8 THEN $9 ELSE fallback_url END,
+            password = CASE WHEN $10 THEN $11 ELSE password END,
+            preview = COALESCE($12, preview),
+            visibility = COALESCE($14::url_visibility, visibility),
+            active = COALESCE((CASE WHEN $4 THEN $5 ELSE expires_at END) > NOW(), true)
+                AND COALESCE(times_visited < (CASE WHEN $6 THEN $7 ELSE max_visits END), true),
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+        body.title,
+        body.description,
+        body.expires_at.is_some(),
+        body.expires_at.flatten(),
+        body.max_visits.is_some(),
+        body.max_visits.flatten(),
+        body.fallback_url.is_some(),
+        body.fallback_url.clone().flatten(),
+        password_hash.is_some(),
+        password_hash.flatten(),
+        body.preview,
+        body.long_url,
+        body.visibility as Option<Visibility>,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn set_long_url(
+    db: &mut PgConnection,
+    id: Uuid,
+    long_url: &str,
+) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            long_url = $2,
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+        long_url,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn delete_url(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            deleted_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_deleted_url(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        SELECT
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!"
+        FROM urls
+        WHERE id = $1 AND deleted_at IS NOT NULL;
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_deleted_urls(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    limit: i64,
+) -> Result<Vec<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        SELECT
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!"
+        FROM urls
+        WHERE
+            deleted_at IS NOT NULL
+            AND (
+                (workspace_id IS NULL AND creator = $1)
+                OR workspace_id IN (
+                    SELECT workspace_id
+                    FROM workspace_members
+                    WHERE user_id = $1 AND role >= 'editor'
+                )
             )
-            .fetch_optional(&mut *db)
-            .await
+        ORDER BY deleted_at DESC, id DESC
+        LIMIT $2;
+        "#,
+        user_id,
+        limit,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn restore_url(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            deleted_at = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NOT NULL
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn purge_deleted_urls(
+    db: &mut PgConnection,
+    retention_sec: i64,
+) -> Result<PgQueryResult, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM urls
+        WHERE deleted_at <= NOW() - $1 * INTERVAL '1 second';
+        "#,
+        retention_sec as f64,
+    )
+    .execute(&mut *db)
+    .await
+}
+
+pub async fn get_url_by_short_url(
+    db: &mut PgConnection,
+    short_url: &str,
+) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        SELECT
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!"
+        FROM urls
+        WHERE short_url = $1 AND deleted_at IS NULL;
+        "#,
+        short_url,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn register_visit(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            times_visited = times_visited + 1,
+            active = max_visits IS NULL OR times_visited + 1 < max_visits
+        WHERE
+            id = $1
+            AND deleted_at IS NULL
+            AND (expires_at IS NULL OR expires_at > NOW())
+            AND (max_visits IS NULL OR times_visited < max_visits)
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn insert_click(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    visitor: &Visitor,
+) -> Result<Click, sqlx::Error> {
+    sqlx::query_as!(
+        Click,
+        r#"
+        INSERT INTO clicks (
+            url_id,
+            referrer,
+            user_agent,
+            ip_hash,
+            accept_language,
+            country,
+            browser,
+            os
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *;
+        "#,
+        url_id,
+        visitor.referrer,
+        visitor.user_agent,
+        visitor.ip_hash,
+        visitor.accept_language,
+        visitor.country,
+        visitor.browser,
+        visitor.os,
+    )
+    .fetch_one(&mut *db)
+    .await
+}
+
+pub async fn get_clicks_by_url(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    limit: i64,
+) -> Result<Vec<Click>, sqlx::Error> {
+    sqlx::query_as!(
+        Click,
+        r#"
+        SELECT *
+        FROM clicks
+        WHERE url_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2;
+        "#,
+        url_id,
+        limit,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+impl StatsScope {
+    fn ids(self) -> (Option<Uuid>, Option<Uuid>) {
+        match self {
+            StatsScope::Url(id) => (Some(id), None),
+            StatsScope::User(id) => (None, Some(id)),
         }
-        (Some(title), None) => {
-            sqlx::query_as!(
-                Url,
-                r#"
-                UPDATE urls SET
-                    title = $2,
-                    updated_at = NOW()
-                WHERE id = $1
-                RETURNING *;
-                "#,
-                id,
-                title
+    }
+}
+
+pub async fn get_click_totals(
+    db: &mut PgConnection,
+    scope: StatsScope,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<(i64, i64), sqlx::Error> {
+    let (url_id, user_id) = scope.ids();
+    let totals = sqlx::query!(
+        r#"
+        SELECT count(*) AS "clicks!", count(DISTINCT ip_hash) AS "unique_visitors!"
+        FROM clicks
+        WHERE
+            url_id IN (
+                SELECT id FROM urls
+                WHERE
+                    (
+                        id = $1
+                        OR (workspace_id IS NULL AND creator = $2)
+                        OR workspace_id IN (
+                            SELECT workspace_id FROM workspace_members WHERE user_id = $2
+                        )
+                    )
+                    AND deleted_at IS NULL
             )
-            .fetch_optional(&mut *db)
-            .await
-        }
-        (Some(title), Some(description)) => {
-            sqlx::query_as!(
-                Url,
-                r#"
-                UPDATE urls SET
-                    title = $2,
-                    description = $3,
-                    updated_at = NOW()
-                WHERE id = $1
-                RETURNING *;
-                "#,
-                id,
-                title,
-                description
+            AND created_at >= $3
+            AND created_at < $4;
+        "#,
+        url_id,
+        user_id,
+        from,
+        to,
+    )
+    .fetch_one(&mut *db)
+    .await?;
+
+    Ok((totals.clicks, totals.unique_visitors))
+}
+
+/// Counts clicks in buckets of local time in the time zone `tz`, including
+/// empty buckets. The range is given in UTC, as well as in local time as
+/// `local_from` and `local_to`.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_click_series(
+    db: &mut PgConnection,
+    scope: StatsScope,
+    interval: StatsInterval,
+    tz: &str,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    local_from: NaiveDateTime,
+    local_to: NaiveDateTime,
+) -> Result<Vec<StatsBucket>, sqlx::Error> {
+    let (url_id, user_id) = scope.ids();
+
+    sqlx::query_as!(
+        StatsBucket,
+        r#"
+        WITH counts AS (
+            SELECT
+                date_trunc($1, (created_at AT TIME ZONE 'UTC') AT TIME ZONE $2) AS bucket,
+                count(*) AS clicks
+            FROM clicks
+            WHERE
+                url_id IN (
+                    SELECT id FROM urls
+                    WHERE
+                        (
+                            id = $3
+                            OR (workspace_id IS NULL AND creator = $4)
+                            OR workspace_id IN (
+                                SELECT workspace_id FROM workspace_members WHERE user_id = $4
+                            )
+                        )
+                        AND deleted_at IS NULL
+                )
+                AND created_at >= $5
+                AND created_at < $6
+            GROUP BY 1
+        )
+        SELECT series.bucket AS "bucket!", COALESCE(counts.clicks, 0) AS "clicks!"
+        FROM generate_series(
+            date_trunc($1, $7::timestamp),
+            $8::timestamp - interval '1 microsecond',
+            ('1 ' || $1)::interval
+        ) AS series(bucket)
+        LEFT JOIN counts ON counts.bucket = series.bucket
+        ORDER BY series.bucket;
+        "#,
+        interval.as_str(),
+        tz,
+        url_id,
+        user_id,
+        from,
+        to,
+        local_from,
+        local_to,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+/// Most frequent values of a dimension, where referrers are grouped by host.
+pub async fn get_top_clicks(
+    db: &mut PgConnection,
+    scope: StatsScope,
+    dimension: StatsDimension,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    limit: i64,
+) -> Result<Vec<StatsEntry>, sqlx::Error> {
+    let (url_id, user_id) = scope.ids();
+
+    sqlx::query_as!(
+        StatsEntry,
+        r#"
+        SELECT
+            CASE $1
+                WHEN 'referrer' THEN regexp_replace(
+                    lower(substring(referrer FROM '^[A-Za-z][A-Za-z0-9+.-]*://([^/:?#]+)')),
+                    '^www\.',
+                    ''
+                )
+                WHEN 'country' THEN country::varchar
+                WHEN 'browser' THEN browser
+                WHEN 'os' THEN os
+            END AS name,
+            count(*) AS "clicks!"
+        FROM clicks
+        WHERE
+            url_id IN (
+                SELECT id FROM urls
+                WHERE
+                    (
+                        id = $2
+                        OR (workspace_id IS NULL AND creator = $3)
+                        OR workspace_id IN (
+                            SELECT workspace_id FROM workspace_members WHERE user_id = $3
+                        )
+                    )
+                    AND deleted_at IS NULL
             )
-            .fetch_optional(&mut *db)
-            .await
-        }
-    }
+            AND created_at >= $4
+            AND created_at < $5
+        GROUP BY 1
+        ORDER BY 2 DESC, 1
+        LIMIT $6;
+        "#,
+        dimension.as_str(),
+        url_id,
+        user_id,
+        from,
+        to,
+        limit,
+    )
+    .fetch_all(&mut *db)
+    .await
 }
 
-pub async fn delete_url(db: &mut PgConnection, id: Uuid) -> Result<Option<Url>, sqlx::Error> {
-    sqlx::query_as!(Url, "DELETE FROM urls WHERE id = $1 RETURNING *;", id,)
-        .fetch_optional(&mut *db)
+pub async fn next_short_url_seq(db: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT nextval('short_url_seq') AS "n!";"#)
+        .fetch_one(&mut *db)
         .await
 }
+
+pub async fn deactivate_expired_urls(db: &mut PgConnection) -> Result<PgQueryResult, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE urls SET
+            active = false
+        WHERE
+            active
+            AND deleted_at IS NULL
+            AND (expires_at <= NOW() OR times_visited >= max_visits);
+        "#
+    )
+    .execute(&mut *db)
+    .await
+}
+
+pub async fn get_username(db: &mut PgConnection, user_id: Uuid) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar!("SELECT username FROM users WHERE id = $1;", user_id)
+        .fetch_one(&mut *db)
+        .await
+}
+
+pub async fn is_private_profile(db: &mut PgConnection, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!("SELECT private_profile FROM users WHERE id = $1;", user_id)
+        .fetch_one(&mut *db)
+        .await
+}
+
+pub async fn search_urls(
+    db: &mut PgConnection,
+    query: &SearchQuery,
+    creator: Option<Uuid>,
+    viewer: Uuid,
+    limit: i64,
+) -> Result<Vec<Url>, sqlx::Error> {
+    let (tags, min_matches) = tag_requirement(&query.tags, query.tag_match);
+
+    sqlx::query_as!(
+        Url,
+        r#"
+        SELECT
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!"
+        FROM urls, websearch_to_tsquery('english', $1) query
+        WHERE
+            search @@ query
+            AND deleted_at IS NULL
+            AND ($2::uuid IS NULL OR creator = $2)
+            AND (
+                (workspace_id IS NULL AND creator = $6)
+                OR workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $6)
+                OR (
+                    visibility = 'public'
+                    AND NOT EXISTS (
+                        SELECT 1 FROM users WHERE users.id = creator AND users.private_profile
+                    )
+                )
+            )
+            AND (
+                cardinality($4::varchar[]) = 0
+                OR (
+                    SELECT count(DISTINCT tags.name)
+                    FROM url_tags
+                    JOIN tags ON tags.id = url_tags.tag_id
+                    WHERE url_tags.url_id = urls.id AND tags.name = ANY($4)
+                ) >= $5
+            )
+        ORDER BY ts_rank(search, query) DESC, created_at DESC
+        LIMIT $3;
+        "#,
+        query.q,
+        creator,
+        limit,
+        &tags,
+        min_matches,
+        viewer,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn set_url_tags(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    owner: Uuid,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO tags (owner, name)
+        SELECT $1, unnest($2::varchar[])
+        ON CONFLICT (owner, name) DO NOTHING;
+        "#,
+        owner,
+        tags,
+    )
+    .execute(&mut *db)
+    .await?;
+
+    sqlx::query!("DELETE FROM url_tags WHERE url_id = $1;", url_id)
+        .execute(&mut *db)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO url_tags (url_id, tag_id)
+        SELECT $1, id
+        FROM tags
+        WHERE owner = $2 AND name = ANY($3);
+        "#,
+        url_id,
+        owner,
+        tags,
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert_revision(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    editor: Uuid,
+    old_long_url: Option<&str>,
+    new_long_url: &str,
+) -> Result<Revision, sqlx::Error> {
+    sqlx::query_as!(
+        Revision,
+        r#"
+        INSERT INTO url_revisions (url_id, editor, old_long_url, new_long_url)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *;
+        "#,
+        url_id,
+        editor,
+        old_long_url,
+        new_long_url,
+    )
+    .fetch_one(&mut *db)
+    .await
+}
+
+pub async fn get_revisions_by_url(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    limit: i64,
+) -> Result<Vec<Revision>, sqlx::Error> {
+    sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT *
+        FROM url_revisions
+        WHERE url_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2;
+        "#,
+        url_id,
+        limit,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_revision(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    id: i64,
+) -> Result<Option<Revision>, sqlx::Error> {
+    sqlx::query_as!(
+        Revision,
+        "SELECT * FROM url_revisions WHERE url_id = $1 AND id = $2;",
+        url_id,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn insert_transfer(
+    db: &mut PgConnection,
+    url_id: Uuid,
+    from_user: Uuid,
+    to_user: Option<Uuid>,
+    to_workspace: Option<Uuid>,
+) -> Result<Option<Transfer>, sqlx::Error> {
+    sqlx::query_as!(
+        Transfer,
+        r#"
+        INSERT INTO url_transfers (url_id, from_user, to_user, to_workspace)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (url_id) WHERE status = 'pending' DO NOTHING
+        RETURNING
+            id, url_id, from_user, to_user, to_workspace,
+            status AS "status: TransferStatus", created_at, resolved_at;
+        "#,
+        url_id,
+        from_user,
+        to_user,
+        to_workspace,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_transfer(
+    db: &mut PgConnection,
+    id: Uuid,
+) -> Result<Option<Transfer>, sqlx::Error> {
+    sqlx::query_as!(
+        Transfer,
+        r#"
+        SELECT
+            id, url_id, from_user, to_user, to_workspace,
+            status AS "status: TransferStatus", created_at, resolved_at
+        FROM url_transfers
+        WHERE id = $1;
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_pending_transfers(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<Transfer>, sqlx::Error> {
+    sqlx::query_as!(
+        Transfer,
+        r#"
+        SELECT
+            id, url_id, from_user, to_user, to_workspace,
+            status AS "status: TransferStatus", created_at, resolved_at
+        FROM url_transfers
+        WHERE
+            status = 'pending'
+            AND (
+                from_user = $1
+                OR to_user = $1
+                OR to_workspace IN (
+                    SELECT workspace_id
+                    FROM workspace_members
+                    WHERE user_id = $1 AND role >= 'admin'
+                )
+            )
+        ORDER BY created_at DESC, id;
+        "#,
+        user_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn resolve_transfer(
+    db: &mut PgConnection,
+    id: Uuid,
+    status: TransferStatus,
+) -> Result<Option<Transfer>, sqlx::Error> {
+    sqlx::query_as!(
+        Transfer,
+        r#"
+        UPDATE url_transfers SET
+            status = $2,
+            resolved_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING
+            id, url_id, from_user, to_user, to_workspace,
+            status AS "status: TransferStatus", created_at, resolved_at;
+        "#,
+        id,
+        status as TransferStatus,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn transfer_url(
+    db: &mut PgConnection,
+    id: Uuid,
+    creator: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<Option<Url>, sqlx::Error> {
+    sqlx::query_as!(
+        Url,
+        r#"
+        UPDATE urls SET
+            creator = $2,
+            workspace_id = $3,
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING
+            id, creator, workspace_id, title, description, long_url, short_url,
+            times_visited, created_at, updated_at, expires_at, max_visits, fallback_url,
+            active, password, preview, visibility AS "visibility: Visibility", deleted_at,
+            url_tag_names(id) AS "tags!";
+        "#,
+        id,
+        creator,
+        workspace_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub fn export_urls(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    with_clicks: bool,
+) -> BoxStream<'_, Result<ExportRow, sqlx::Error>> {
+    sqlx::query_as!(
+        ExportRow,
+        r#"
+        SELECT
+            id, workspace_id, title, description, long_url, short_url, times_visited,
+            created_at, updated_at, expires_at, max_visits, fallback_url, active,
+            password IS NOT NULL AS "password_protected!", preview,
+            visibility AS "visibility: Visibility", url_tag_names(id) AS "tags!",
+            CASE WHEN $2 THEN (SELECT count(*) FROM clicks WHERE url_id = urls.id) END
+                AS clicks,
+            CASE WHEN $2 THEN (SELECT max(created_at) FROM clicks WHERE url_id = urls.id) END
+                AS last_clicked_at
+        FROM urls
+        WHERE
+            deleted_at IS NULL
+            AND (
+                (workspace_id IS NULL AND creator = $1)
+                OR workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1)
+            )
+        ORDER BY created_at, id;
+        "#,
+        user_id,
+        with_clicks,
+    )
+    .fetch(&mut *db)
+}
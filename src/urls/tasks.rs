@@ -0,0 +1,59 @@
+use crate::{config::Config, db::Db, urls::repo};
+use rocket::{fairing::AdHoc, tokio};
+use rocket_db_pools::Database;
+use std::time::Duration;
+
+pub fn expiration() -> AdHoc {
+    AdHoc::on_liftoff("Link Expiration", |rocket| {
+        Box::pin(async move {
+            let config = rocket.state::<Config>().unwrap();
+            let pool = (**Db::fetch(rocket).unwrap()).clone();
+            let period = Duration::from_secs(config.expiration_interval_sec);
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+
+                loop {
+                    interval.tick().await;
+
+                    let result = match pool.acquire().await {
+                        Ok(mut db) => repo::deactivate_expired_urls(&mut db).await,
+                        Err(e) => Err(e),
+                    };
+
+                    if let Err(e) = result {
+                        rocket::error!("failed to deactivate expired links: {}", e);
+                    }
+                }
+            });
+        })
+    })
+}
+
+pub fn purge() -> AdHoc {
+    AdHoc::on_liftoff("Trash Purge", |rocket| {
+        Box::pin(async move {
+            let config = rocket.state::<Config>().unwrap();
+            let pool = (**Db::fetch(rocket).unwrap()).clone();
+            let period = Duration::from_secs(config.purge_interval_sec);
+            let retention_sec = config.trash_retention_sec as i64;
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+
+                loop {
+                    interval.tick().await;
+
+                    let result = match pool.acquire().await {
+                        Ok(mut db) => repo::purge_deleted_urls(&mut db, retention_sec).await,
+                        Err(e) => Err(e),
+                    };
+
+                    if let Err(e) = result {
+                        rocket::error!("failed to purge deleted links: {}", e);
+                    }
+                }
+            });
+        })
+    })
+}
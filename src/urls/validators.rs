@@ -1,7 +1,30 @@
+use crate::tags::validators::is_valid_tag;
+use sqlx::types::chrono::NaiveDateTime;
 use url::Url;
 
+const RESERVED_ALIASES: [&str; 15] = [
+    "admin",
+    "api",
+    "assets",
+    "auth",
+    "health",
+    "login",
+    "logout",
+    "signin",
+    "signup",
+    "static",
+    "tags",
+    "urls",
+    "users",
+    "webhooks",
+    "workspaces",
+];
+
+/// Only web URLs are accepted, as destinations end up in links of the pages
+/// served by the shortener, where e.g. `javascript:` would run on its origin.
 pub fn is_valid_long_url(url: &str) -> bool {
-    url.len() <= 2048 && Url::parse(url).is_ok()
+    url.len() <= 2048
+        && Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.has_host())
 }
 
 pub fn is_valid_title(title: &str) -> bool {
@@ -11,3 +34,30 @@ pub fn is_valid_title(title: &str) -> bool {
 pub fn is_valid_description(description: &str) -> bool {
     description.len() <= 256
 }
+
+pub fn is_valid_alias(alias: &str) -> bool {
+    !alias.is_empty()
+        && alias.len() <= 16
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !RESERVED_ALIASES
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(alias))
+}
+
+pub fn is_valid_expiration(expires_at: NaiveDateTime) -> bool {
+    expires_at > chrono::Utc::now().naive_utc()
+}
+
+pub fn is_valid_max_visits(max_visits: i32) -> bool {
+    max_visits > 0
+}
+
+pub fn is_valid_link_password(password: &str) -> bool {
+    !password.is_empty() && password.len() <= 128
+}
+
+pub fn is_valid_tag_list(tags: &[String]) -> bool {
+    tags.len() <= 20 && tags.iter().all(|t| is_valid_tag(t))
+}
@@ -1,7 +1,128 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use rand::Rng;
+use rocket::{
+    form::{self, FromFormField, ValueField},
+    serde::{Deserialize, Deserializer, Serializer},
+};
 
 pub fn compute_random_32_bytes_key() -> String {
     let mut bytes = [0u8; 32];
     rand::thread_rng().fill(&mut bytes);
     hex::encode(bytes)
 }
+
+/// Distinguishes a field set to `null` (`Some(None)`) from a missing one
+/// (`None`) when combined with `#[serde(default)]`.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Serializes an optional secret as whether it is set, without exposing it.
+pub fn serialize_is_some<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bool(value.is_some())
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+pub fn unescape_html(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                n if n.starts_with("#x") || n.starts_with("#X") => u32::from_str_radix(&n[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                n if n.starts_with('#') => n[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+
+            c.map(|c| (c, end))
+        });
+
+        match entity {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Query string timestamp, given either as a date or as a date and time.
+pub struct Timestamp(pub NaiveDateTime);
+
+impl<'v> FromFormField<'v> for Timestamp {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        field
+            .value
+            .parse::<NaiveDateTime>()
+            .or_else(|_| {
+                field
+                    .value
+                    .parse::<NaiveDate>()
+                    .map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+            })
+            .map(Timestamp)
+            .map_err(|_| form::Error::validation("invalid timestamp").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_and_numeric_entities_are_unescaped() {
+        assert_eq!(
+            unescape_html("&lt;a&gt; &amp; &quot;b&quot; &apos;c&apos;"),
+            "<a> & \"b\" 'c'"
+        );
+        assert_eq!(unescape_html("&#x41;&#X62;&#67;&nbsp;"), "AbC\u{a0}");
+        assert_eq!(unescape_html("&#x1F600;"), "\u{1F600}");
+    }
+
+    #[test]
+    fn unknown_or_broken_entities_are_kept() {
+        assert_eq!(unescape_html("a & b"), "a & b");
+        assert_eq!(
+            unescape_html("&bogus; &#xZZ; &#xD800;"),
+            "&bogus; &#xZZ; &#xD800;"
+        );
+        assert_eq!(unescape_html("&amp"), "&amp");
+        assert_eq!(
+            unescape_html("&verylongentityname;"),
+            "&verylongentityname;"
+        );
+    }
+}
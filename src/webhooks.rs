@@ -0,0 +1,116 @@
+use crate::Validate;
+use hmac::{Hmac, Mac};
+use rocket::serde::{uuid::Uuid, Deserialize, Serialize};
+use rocket_db_pools::sqlx;
+use sha2::Sha256;
+
+pub mod handlers;
+pub(crate) mod repo;
+pub mod tasks;
+mod validators;
+
+#[derive(Deserialize, Serialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "delivery_status", rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    id: Uuid,
+    url: String,
+    events: Vec<String>,
+    active: bool,
+    created_at: sqlx::types::chrono::NaiveDateTime,
+    updated_at: sqlx::types::chrono::NaiveDateTime,
+}
+
+/// A webhook along with its signing secret, which is only shown when it is
+/// created or rotated.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct WebhookSecret {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    id: Uuid,
+    redelivery_of: Option<Uuid>,
+    event: String,
+    status: DeliveryStatus,
+    attempts: i32,
+    response_status: Option<i32>,
+    error: Option<String>,
+    created_at: sqlx::types::chrono::NaiveDateTime,
+    last_attempt_at: Option<sqlx::types::chrono::NaiveDateTime>,
+    next_attempt_at: Option<sqlx::types::chrono::NaiveDateTime>,
+    delivered_at: Option<sqlx::types::chrono::NaiveDateTime>,
+}
+
+/// A delivery claimed by the dispatcher, along with where to send it.
+pub struct PendingDelivery {
+    id: Uuid,
+    event: String,
+    payload: String,
+    url: String,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct WebhookBody {
+    url: String,
+    events: Vec<String>,
+    active: Option<bool>,
+}
+
+impl Validate for WebhookBody {
+    fn validate(&self) -> bool {
+        validators::is_valid_endpoint(&self.url) && validators::is_valid_event_list(&self.events)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PatchWebhookBody {
+    url: Option<String>,
+    events: Option<Vec<String>>,
+    active: Option<bool>,
+}
+
+impl Validate for PatchWebhookBody {
+    fn validate(&self) -> bool {
+        self.url
+            .as_deref()
+            .is_none_or(validators::is_valid_endpoint)
+            && self
+                .events
+                .as_deref()
+                .is_none_or(validators::is_valid_event_list)
+    }
+}
+
+/// Signs a delivery the way receivers are expected to verify it: the HMAC-SHA256
+/// of `"{timestamp}.{payload}"` keyed with the webhook secret, hex encoded.
+///
+/// Both are sent in the `Urlessen-Signature` header as `t={timestamp},v1={signature}`,
+/// so receivers can also reject deliveries that are too old to be legitimate.
+pub fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
@@ -0,0 +1,160 @@
+use super::{
+    repo, validators::is_public_endpoint, Delivery, PatchWebhookBody, Webhook, WebhookBody,
+    WebhookSecret,
+};
+use crate::{
+    auth::{
+        scopes::{WebhooksRead, WebhooksWrite},
+        Scoped,
+    },
+    config::Config,
+    db::Db,
+    utils::compute_random_32_bytes_key,
+    Validate,
+};
+use rocket::{http::Status, serde::json::Json, State};
+use rocket_db_pools::Connection;
+use sqlx::types::Uuid;
+
+#[rocket::get("/")]
+pub async fn get_webhooks(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksRead>,
+) -> Result<Json<Vec<Webhook>>, Status> {
+    let webhooks = repo::get_webhooks(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(webhooks))
+}
+
+#[rocket::post("/", data = "<body>")]
+pub async fn create_webhook(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksWrite>,
+    body: Json<WebhookBody>,
+    config: &State<Config>,
+) -> Result<Json<WebhookSecret>, Status> {
+    if !body.validate() || !(config.webhook_allow_private_hosts || is_public_endpoint(&body.url)) {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let secret = compute_random_32_bytes_key();
+    let webhook = repo::insert_webhook(
+        &mut db,
+        user.id,
+        &body.url,
+        &secret,
+        &body.events,
+        body.active.unwrap_or(true),
+    )
+    .await
+    .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(WebhookSecret { webhook, secret }))
+}
+
+#[rocket::get("/<id>")]
+pub async fn get_webhook(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksRead>,
+    id: Uuid,
+) -> Result<Json<Webhook>, Status> {
+    let webhook = repo::get_webhook(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(webhook))
+}
+
+#[rocket::patch("/<id>", data = "<body>")]
+pub async fn patch_webhook(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksWrite>,
+    id: Uuid,
+    body: Json<PatchWebhookBody>,
+    config: &State<Config>,
+) -> Result<Json<Webhook>, Status> {
+    let allowed = |url: &str| config.webhook_allow_private_hosts || is_public_endpoint(url);
+
+    if !body.validate() || !body.url.as_deref().is_none_or(allowed) {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let webhook = repo::patch_webhook(&mut db, id, user.id, &body)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(webhook))
+}
+
+#[rocket::post("/<id>/secret")]
+pub async fn rotate_webhook_secret(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksWrite>,
+    id: Uuid,
+) -> Result<Json<WebhookSecret>, Status> {
+    let secret = compute_random_32_bytes_key();
+    let webhook = repo::set_webhook_secret(&mut db, id, user.id, &secret)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(WebhookSecret { webhook, secret }))
+}
+
+#[rocket::delete("/<id>")]
+pub async fn delete_webhook(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksWrite>,
+    id: Uuid,
+) -> Result<Json<Webhook>, Status> {
+    let webhook = repo::delete_webhook(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(webhook))
+}
+
+#[rocket::get("/<id>/deliveries?<limit>")]
+pub async fn get_deliveries(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksRead>,
+    id: Uuid,
+    limit: Option<u16>,
+) -> Result<Json<Vec<Delivery>>, Status> {
+    repo::get_webhook(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    let deliveries = repo::get_deliveries(&mut db, id, limit.into())
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(deliveries))
+}
+
+#[rocket::post("/<id>/deliveries/<delivery_id>/redeliver")]
+pub async fn redeliver(
+    mut db: Connection<Db>,
+    user: Scoped<WebhooksWrite>,
+    id: Uuid,
+    delivery_id: Uuid,
+) -> Result<Json<Delivery>, Status> {
+    repo::get_webhook(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let delivery = repo::insert_redelivery(&mut db, delivery_id, id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(delivery))
+}
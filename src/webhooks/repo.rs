@@ -0,0 +1,301 @@
+use super::{Delivery, DeliveryStatus, PatchWebhookBody, PendingDelivery, Webhook};
+use crate::events::Event;
+use rocket::serde::json;
+use sqlx::{types::Uuid, PgConnection};
+
+pub async fn get_webhooks(db: &mut PgConnection, owner: Uuid) -> Result<Vec<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT id, url, events, active, created_at, updated_at
+        FROM webhooks
+        WHERE owner = $1
+        ORDER BY created_at, id;
+        "#,
+        owner,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_webhook(
+    db: &mut PgConnection,
+    id: Uuid,
+    owner: Uuid,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT id, url, events, active, created_at, updated_at
+        FROM webhooks
+        WHERE id = $1 AND owner = $2;
+        "#,
+        id,
+        owner,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn insert_webhook(
+    db: &mut PgConnection,
+    owner: Uuid,
+    url: &str,
+    secret: &str,
+    events: &[String],
+    active: bool,
+) -> Result<Webhook, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        INSERT INTO webhooks (owner, url, secret, events, active)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, url, events, active, created_at, updated_at;
+        "#,
+        owner,
+        url,
+        secret,
+        events,
+        active,
+    )
+    .fetch_one(&mut *db)
+    .await
+}
+
+pub async fn patch_webhook(
+    db: &mut PgConnection,
+    id: Uuid,
+    owner: Uuid,
+    body: &PatchWebhookBody,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        UPDATE webhooks
+        SET
+            url = COALESCE($3, url),
+            events = COALESCE($4, events),
+            active = COALESCE($5, active),
+            updated_at = NOW()
+        WHERE id = $1 AND owner = $2
+        RETURNING id, url, events, active, created_at, updated_at;
+        "#,
+        id,
+        owner,
+        body.url,
+        body.events.as_deref(),
+        body.active,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn set_webhook_secret(
+    db: &mut PgConnection,
+    id: Uuid,
+    owner: Uuid,
+    secret: &str,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        UPDATE webhooks
+        SET secret = $3, updated_at = NOW()
+        WHERE id = $1 AND owner = $2
+        RETURNING id, url, events, active, created_at, updated_at;
+        "#,
+        id,
+        owner,
+        secret,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn delete_webhook(
+    db: &mut PgConnection,
+    id: Uuid,
+    owner: Uuid,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        DELETE FROM webhooks
+        WHERE id = $1 AND owner = $2
+        RETURNING id, url, events, active, created_at, updated_at;
+        "#,
+        id,
+        owner,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_deliveries(
+    db: &mut PgConnection,
+    webhook_id: Uuid,
+    limit: i64,
+) -> Result<Vec<Delivery>, sqlx::Error> {
+    sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT
+            id, redelivery_of, event, status AS "status: DeliveryStatus", attempts,
+            response_status, error, created_at, last_attempt_at, next_attempt_at, delivered_at
+        FROM webhook_deliveries
+        WHERE webhook_id = $1
+        ORDER BY created_at DESC, id
+        LIMIT $2;
+        "#,
+        webhook_id,
+        limit,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+/// Queues a delivery of the event to every active webhook subscribed to it
+/// whose owner can see the link, as [`crate::events::Audience`] does.
+pub async fn insert_deliveries(db: &mut PgConnection, event: &Event) -> Result<u64, sqlx::Error> {
+    let payload = json::to_string(&event.payload).unwrap_or_default();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event, payload)
+        SELECT id, $1::varchar, $2
+        FROM webhooks
+        WHERE active AND $1::varchar = ANY(events) AND CASE
+            WHEN $4::uuid IS NULL THEN owner = $3
+            ELSE owner IN (SELECT user_id FROM workspace_members WHERE workspace_id = $4)
+        END;
+        "#,
+        event.payload.name(),
+        payload,
+        event.creator,
+        event.workspace_id,
+    )
+    .execute(&mut *db)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+/// Queues the payload of a previous delivery again, keeping the original one
+/// in the log untouched.
+pub async fn insert_redelivery(
+    db: &mut PgConnection,
+    id: Uuid,
+    webhook_id: Uuid,
+) -> Result<Option<Delivery>, sqlx::Error> {
+    sqlx::query_as!(
+        Delivery,
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, redelivery_of, event, payload)
+        SELECT webhook_id, id, event, payload
+        FROM webhook_deliveries
+        WHERE id = $1 AND webhook_id = $2
+        RETURNING
+            id, redelivery_of, event, status AS "status: DeliveryStatus", attempts,
+            response_status, error, created_at, last_attempt_at, next_attempt_at, delivered_at;
+        "#,
+        id,
+        webhook_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+/// Claims up to `limit` due deliveries of active webhooks, postponing them by
+/// `lease_sec` so that they are retried if the dispatcher dies midway.
+pub async fn claim_deliveries(
+    db: &mut PgConnection,
+    limit: i64,
+    lease_sec: f64,
+) -> Result<Vec<PendingDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        PendingDelivery,
+        r#"
+        UPDATE webhook_deliveries
+        SET next_attempt_at = NOW() + make_interval(secs => $2)
+        FROM webhooks
+        WHERE webhooks.id = webhook_deliveries.webhook_id AND webhook_deliveries.id IN (
+            SELECT webhook_deliveries.id
+            FROM webhook_deliveries
+            JOIN webhooks ON webhooks.id = webhook_deliveries.webhook_id
+            WHERE
+                webhook_deliveries.status = 'pending'
+                AND webhook_deliveries.next_attempt_at <= NOW()
+                AND webhooks.active
+            ORDER BY webhook_deliveries.next_attempt_at
+            LIMIT $1
+            FOR UPDATE OF webhook_deliveries SKIP LOCKED
+        )
+        RETURNING
+            webhook_deliveries.id, webhook_deliveries.event, webhook_deliveries.payload,
+            webhooks.url, webhooks.secret;
+        "#,
+        limit,
+        lease_sec,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+/// Deletes the deliveries that are no longer pending and were queued more than
+/// `retention_sec` ago.
+pub async fn purge_deliveries(
+    db: &mut PgConnection,
+    retention_sec: i64,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM webhook_deliveries
+        WHERE status <> 'pending' AND created_at <= NOW() - make_interval(secs => $1);
+        "#,
+        retention_sec as f64,
+    )
+    .execute(&mut *db)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+/// Records an attempt, scheduling the next one `backoff_sec` times two to the
+/// number of previous attempts from now, unless it succeeded or it was the
+/// last one allowed.
+pub async fn record_attempt(
+    db: &mut PgConnection,
+    id: Uuid,
+    response_status: Option<i32>,
+    error: Option<&str>,
+    max_attempts: i32,
+    backoff_sec: f64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries
+        SET
+            attempts = attempts + 1,
+            response_status = $2,
+            error = $3,
+            last_attempt_at = NOW(),
+            status = CASE
+                WHEN $2 BETWEEN 200 AND 299 THEN 'delivered'
+                WHEN attempts + 1 >= $4 THEN 'failed'
+                ELSE 'pending'
+            END::delivery_status,
+            delivered_at = CASE WHEN $2 BETWEEN 200 AND 299 THEN NOW() END,
+            next_attempt_at = CASE
+                WHEN $2 BETWEEN 200 AND 299 OR attempts + 1 >= $4 THEN NULL
+                ELSE NOW() + make_interval(secs => $5 * power(2, attempts))
+            END
+        WHERE id = $1;
+        "#,
+        id,
+        response_status,
+        error,
+        max_attempts,
+        backoff_sec,
+    )
+    .execute(&mut *db)
+    .await
+    .map(drop)
+}
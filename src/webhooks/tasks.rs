@@ -0,0 +1,232 @@
+use super::{
+    repo, sign,
+    validators::{is_public_address, is_public_endpoint},
+    PendingDelivery,
+};
+use crate::{config::Config, db::Db};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rocket::{fairing::AdHoc, futures::future::join_all, http::ContentType, tokio};
+use rocket_db_pools::{sqlx::PgPool, Database};
+use std::{error::Error, io, sync::Arc, time::Duration};
+
+const ERROR_MAX_LEN: usize = 256;
+
+/// Periodically sends the due deliveries, retrying failed ones with an
+/// exponential backoff until they succeed or run out of attempts.
+pub fn dispatch() -> AdHoc {
+    AdHoc::on_liftoff("Webhook Dispatch", |rocket| {
+        Box::pin(async move {
+            let config = rocket.state::<Config>().unwrap();
+            let pool = (**Db::fetch(rocket).unwrap()).clone();
+            let period = Duration::from_secs(config.webhook_interval_sec);
+            let timeout = Duration::from_secs(config.webhook_timeout_sec);
+            let batch_size = config.webhook_batch_size as i64;
+            let max_attempts = config.webhook_max_attempts as i32;
+            let backoff_sec = config.webhook_backoff_sec as f64;
+            let allow_private_hosts = config.webhook_allow_private_hosts;
+
+            let mut builder = reqwest::Client::builder()
+                .timeout(timeout)
+                .user_agent(concat!("urlessen/", env!("CARGO_PKG_VERSION")))
+                .redirect(reqwest::redirect::Policy::none());
+
+            if !allow_private_hosts {
+                builder = builder.dns_resolver(Arc::new(PublicResolver));
+            }
+
+            let client = match builder.build() {
+                Ok(client) => client,
+                Err(e) => {
+                    rocket::error!("failed to build webhook client: {}", e);
+                    return;
+                }
+            };
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+
+                loop {
+                    interval.tick().await;
+
+                    // Keep going while there is a backlog, instead of waiting
+                    // for the next tick between full batches
+                    loop {
+                        let sent = dispatch_batch(
+                            &pool,
+                            &client,
+                            batch_size,
+                            timeout.as_secs_f64() * 2.0,
+                            max_attempts,
+                            backoff_sec,
+                            allow_private_hosts,
+                        )
+                        .await;
+
+                        match sent {
+                            Ok(sent) if sent as i64 == batch_size => continue,
+                            Ok(_) => break,
+                            Err(e) => {
+                                rocket::error!("failed to dispatch webhooks: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        })
+    })
+}
+
+/// Periodically deletes the deliveries that are no longer pending and were
+/// queued longer ago than the retention period.
+pub fn prune() -> AdHoc {
+    AdHoc::on_liftoff("Webhook Prune", |rocket| {
+        Box::pin(async move {
+            let config = rocket.state::<Config>().unwrap();
+            let pool = (**Db::fetch(rocket).unwrap()).clone();
+            let period = Duration::from_secs(config.webhook_prune_interval_sec);
+            let retention_sec = config.webhook_retention_sec as i64;
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+
+                loop {
+                    interval.tick().await;
+
+                    let result = match pool.acquire().await {
+                        Ok(mut db) => repo::purge_deliveries(&mut db, retention_sec).await,
+                        Err(e) => Err(e),
+                    };
+
+                    if let Err(e) = result {
+                        rocket::error!("failed to purge webhook deliveries: {}", e);
+                    }
+                }
+            });
+        })
+    })
+}
+
+async fn dispatch_batch(
+    pool: &PgPool,
+    client: &reqwest::Client,
+    batch_size: i64,
+    lease_sec: f64,
+    max_attempts: i32,
+    backoff_sec: f64,
+    allow_private_hosts: bool,
+) -> Result<usize, sqlx::Error> {
+    let deliveries = {
+        let mut db = pool.acquire().await?;
+        repo::claim_deliveries(&mut db, batch_size, lease_sec).await?
+    };
+
+    let results = join_all(
+        deliveries
+            .iter()
+            .map(|d| send(client, d, allow_private_hosts)),
+    )
+    .await;
+
+    let mut db = pool.acquire().await?;
+
+    for (delivery, (status, error)) in deliveries.iter().zip(results) {
+        repo::record_attempt(
+            &mut db,
+            delivery.id,
+            status,
+            error.as_deref(),
+            max_attempts,
+            backoff_sec,
+        )
+        .await?;
+    }
+
+    Ok(deliveries.len())
+}
+
+/// Posts a signed delivery, returning the response status, if any, and what
+/// went wrong when it was not successful.
+async fn send(
+    client: &reqwest::Client,
+    delivery: &PendingDelivery,
+    allow_private_hosts: bool,
+) -> (Option<i32>, Option<String>) {
+    // Addresses in the URL itself never reach the resolver
+    if !allow_private_hosts && !is_public_endpoint(&delivery.url) {
+        return (None, Some(String::from("endpoint host is not public")));
+    }
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign(&delivery.secret, timestamp, &delivery.payload);
+
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, ContentType::JSON.to_string())
+        .header("Urlessen-Event", &delivery.event)
+        .header("Urlessen-Delivery", delivery.id.to_string())
+        .header(
+            "Urlessen-Signature",
+            format!("t={timestamp},v1={signature}"),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16().into()), None)
+        }
+        Ok(response) => (
+            Some(response.status().as_u16().into()),
+            Some(format!("unexpected response status {}", response.status())),
+        ),
+        Err(e) => (None, Some(truncate(&describe(&e)))),
+    }
+}
+
+/// Joins the error with its sources, as reqwest keeps the actual cause of
+/// failed connections, e.g. a refused or blocked address, in the latter.
+fn describe(error: &dyn Error) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+
+    while let Some(e) = source {
+        description.push_str(": ");
+        description.push_str(&e.to_string());
+        source = e.source();
+    }
+
+    description
+}
+
+fn truncate(error: &str) -> String {
+    let mut end = error.len().min(ERROR_MAX_LEN);
+
+    while !error.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    error[..end].to_string()
+}
+
+/// Resolves names with the system resolver, leaving out the addresses that are
+/// not public so that webhooks cannot be pointed at internal services.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect::<Vec<_>>();
+
+            if addrs.is_empty() {
+                return Err(io::Error::other("host does not resolve to a public address").into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
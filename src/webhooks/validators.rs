@@ -0,0 +1,70 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+const EVENTS: [&str; 4] = ["click", "link-created", "link-updated", "link-deleted"];
+
+pub fn is_valid_endpoint(url: &str) -> bool {
+    url.len() <= 2048
+        && Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.has_host())
+}
+
+/// Whether the endpoint is not obviously internal, that is, its host is not
+/// `localhost` nor a loopback, private, link-local or otherwise reserved
+/// address. Names resolving to such addresses are caught by the dispatcher.
+pub fn is_public_endpoint(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|u| match u.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_public_address(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_address(IpAddr::V6(ip)),
+        None => false,
+    })
+}
+
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space of carrier-grade NATs, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // Reserved for future use, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        || ip.is_multicast()
+        // Documentation prefix, 2001:db8::/32
+        || (ip.segments()[0] == 0x2001 && ip.segments()[1] == 0xdb8))
+}
+
+pub fn is_valid_event_list(events: &[String]) -> bool {
+    !events.is_empty()
+        && events.iter().all(|e| EVENTS.contains(&e.as_str()))
+        && events
+            .iter()
+            .enumerate()
+            .all(|(i, e)| !events[..i].contains(e))
+}
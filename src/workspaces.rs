@@ -0,0 +1,85 @@
+use crate::Validate;
+use rocket::{
+    http::Status,
+    serde::{uuid::Uuid, Deserialize, Serialize},
+};
+use rocket_db_pools::sqlx;
+use sqlx::PgConnection;
+
+pub mod handlers;
+pub(crate) mod repo;
+mod validators;
+
+#[derive(Deserialize, Serialize, sqlx::Type, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "workspace_role", rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+    Owner,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    id: Uuid,
+    name: String,
+    created_at: sqlx::types::chrono::NaiveDateTime,
+    role: Role,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    user_id: Uuid,
+    username: String,
+    role: Role,
+    created_at: sqlx::types::chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct WorkspaceBody {
+    name: String,
+}
+
+impl Validate for WorkspaceBody {
+    fn validate(&self) -> bool {
+        validators::is_valid_workspace_name(&self.name)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MemberBody {
+    username: String,
+    role: Role,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RoleBody {
+    role: Role,
+}
+
+pub async fn require_role(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    required: Role,
+) -> Result<Role, Status> {
+    let role = repo::get_member_role(db, workspace_id, user_id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if role < required {
+        return Err(Status::Forbidden);
+    }
+
+    Ok(role)
+}
@@ -0,0 +1,229 @@
+use super::{repo, require_role, Member, MemberBody, Role, RoleBody, Workspace, WorkspaceBody};
+use crate::{
+    auth::{
+        scopes::{WorkspacesRead, WorkspacesWrite},
+        Scoped,
+    },
+    db::Db,
+    error::ApiError,
+    Validate,
+};
+use rocket::{http::Status, serde::json::Json};
+use rocket_db_pools::Connection;
+use sqlx::{types::Uuid, Connection as _, PgConnection};
+
+#[rocket::get("/")]
+pub async fn get_workspaces(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesRead>,
+) -> Result<Json<Vec<Workspace>>, Status> {
+    let workspaces = repo::get_workspaces_by_member(&mut db, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(workspaces))
+}
+
+#[rocket::post("/", data = "<body>")]
+pub async fn create_workspace(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    body: Json<WorkspaceBody>,
+) -> Result<Json<Workspace>, Status> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let workspace = repo::insert_workspace(&mut db, &body.name, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(workspace))
+}
+
+#[rocket::get("/<id>")]
+pub async fn get_workspace(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesRead>,
+    id: Uuid,
+) -> Result<Json<Workspace>, Status> {
+    let workspace = repo::get_workspace(&mut db, id, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(workspace))
+}
+
+#[rocket::patch("/<id>", data = "<body>")]
+pub async fn rename_workspace(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    id: Uuid,
+    body: Json<WorkspaceBody>,
+) -> Result<Json<Workspace>, Status> {
+    if !body.validate() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    require_role(&mut db, id, user.id, Role::Admin).await?;
+
+    let workspace = repo::rename_workspace(&mut db, id, &body.name, user.id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    Ok(Json(workspace))
+}
+
+#[rocket::delete("/<id>")]
+pub async fn delete_workspace(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    id: Uuid,
+) -> Result<(), Status> {
+    require_role(&mut db, id, user.id, Role::Owner).await?;
+
+    repo::delete_workspace(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))
+}
+
+#[rocket::get("/<id>/members")]
+pub async fn get_members(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesRead>,
+    id: Uuid,
+) -> Result<Json<Vec<Member>>, Status> {
+    require_role(&mut db, id, user.id, Role::Viewer).await?;
+
+    let members = repo::get_members(&mut db, id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(members))
+}
+
+#[rocket::post("/<id>/members", data = "<body>")]
+pub async fn add_member(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    id: Uuid,
+    body: Json<MemberBody>,
+) -> Result<Json<Member>, ApiError> {
+    let role = require_role(&mut db, id, user.id, Role::Admin).await?;
+
+    if body.role > role {
+        return Err(Status::Forbidden.into());
+    }
+
+    let user_id = repo::get_user_id(&mut db, &body.username)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    let member = repo::insert_member(&mut db, id, user_id, body.role)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or_else(|| {
+            ApiError::new(
+                Status::Conflict,
+                "already_member",
+                format!("The user \"{}\" is already a member", body.username),
+            )
+        })?;
+
+    Ok(Json(member))
+}
+
+#[rocket::patch("/<id>/members/<user_id>", data = "<body>")]
+pub async fn patch_member(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    id: Uuid,
+    user_id: Uuid,
+    body: Json<RoleBody>,
+) -> Result<Json<Member>, ApiError> {
+    let role = require_role(&mut db, id, user.id, Role::Admin).await?;
+
+    let current = repo::get_member_role(&mut db, id, user_id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if current > role || body.role > role {
+        return Err(Status::Forbidden.into());
+    }
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    if current == Role::Owner && body.role != Role::Owner {
+        ensure_other_owner(&mut tx, id).await?;
+    }
+
+    let member = repo::update_member_role(&mut tx, id, user_id, body.role)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    Ok(Json(member))
+}
+
+#[rocket::delete("/<id>/members/<user_id>")]
+pub async fn remove_member(
+    mut db: Connection<Db>,
+    user: Scoped<WorkspacesWrite>,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<Json<Member>, ApiError> {
+    let required = if user_id == user.id {
+        Role::Viewer
+    } else {
+        Role::Admin
+    };
+    let role = require_role(&mut db, id, user.id, required).await?;
+
+    let current = repo::get_member_role(&mut db, id, user_id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    if current > role {
+        return Err(Status::Forbidden.into());
+    }
+
+    let mut tx = db.begin().await.or(Err(Status::InternalServerError))?;
+
+    if current == Role::Owner {
+        ensure_other_owner(&mut tx, id).await?;
+    }
+
+    let member = repo::delete_member(&mut tx, id, user_id)
+        .await
+        .or(Err(Status::InternalServerError))?
+        .ok_or(Status::NotFound)?;
+
+    tx.commit().await.or(Err(Status::InternalServerError))?;
+
+    Ok(Json(member))
+}
+
+/// Fails unless the workspace has another owner, whose row stays locked until
+/// the end of the transaction so that two owners cannot step down at once.
+async fn ensure_other_owner(db: &mut PgConnection, workspace_id: Uuid) -> Result<(), ApiError> {
+    let owners = repo::lock_owners(db, workspace_id)
+        .await
+        .or(Err(Status::InternalServerError))?;
+
+    if owners.len() <= 1 {
+        return Err(ApiError::new(
+            Status::Conflict,
+            "last_owner",
+            "A workspace must keep at least one owner",
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use super::{Member, Role, Workspace};
+use sqlx::{types::Uuid, PgConnection};
+
+pub async fn get_workspaces_by_member(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<Workspace>, sqlx::Error> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT
+            workspaces.id, workspaces.name, workspaces.created_at,
+            workspace_members.role AS "role: Role"
+        FROM workspaces
+        JOIN workspace_members ON workspace_members.workspace_id = workspaces.id
+        WHERE workspace_members.user_id = $1
+        ORDER BY workspaces.name, workspaces.id;
+        "#,
+        user_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_workspace_ids_by_member(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r"SELECT workspace_id FROM workspace_members WHERE user_id = $1;",
+        user_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_workspace(
+    db: &mut PgConnection,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Workspace>, sqlx::Error> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT
+            workspaces.id, workspaces.name, workspaces.created_at,
+            workspace_members.role AS "role: Role"
+        FROM workspaces
+        JOIN workspace_members ON workspace_members.workspace_id = workspaces.id
+        WHERE workspaces.id = $1 AND workspace_members.user_id = $2;
+        "#,
+        id,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn insert_workspace(
+    db: &mut PgConnection,
+    name: &str,
+    owner: Uuid,
+) -> Result<Workspace, sqlx::Error> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        WITH workspace AS (
+            INSERT INTO workspaces (name)
+            VALUES ($1)
+            RETURNING *
+        ), member AS (
+            INSERT INTO workspace_members (workspace_id, user_id, role)
+            SELECT id, $2, 'owner'
+            FROM workspace
+            RETURNING *
+        )
+        SELECT
+            workspace.id, workspace.name, workspace.created_at,
+            member.role AS "role: Role"
+        FROM workspace
+        JOIN member ON member.workspace_id = workspace.id;
+        "#,
+        name,
+        owner,
+    )
+    .fetch_one(&mut *db)
+    .await
+}
+
+pub async fn rename_workspace(
+    db: &mut PgConnection,
+    id: Uuid,
+    name: &str,
+    user_id: Uuid,
+) -> Result<Option<Workspace>, sqlx::Error> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        UPDATE workspaces SET
+            name = $2
+        FROM workspace_members
+        WHERE
+            workspaces.id = $1
+            AND workspace_members.workspace_id = workspaces.id
+            AND workspace_members.user_id = $3
+        RETURNING
+            workspaces.id, workspaces.name, workspaces.created_at,
+            workspace_members.role AS "role: Role";
+        "#,
+        id,
+        name,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn delete_workspace(db: &mut PgConnection, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM workspaces WHERE id = $1;", id)
+        .execute(&mut *db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_member_role(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Role>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT role AS "role: Role"
+        FROM workspace_members
+        WHERE workspace_id = $1 AND user_id = $2;
+        "#,
+        workspace_id,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn get_members(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+) -> Result<Vec<Member>, sqlx::Error> {
+    sqlx::query_as!(
+        Member,
+        r#"
+        SELECT
+            workspace_members.user_id, users.username,
+            workspace_members.role AS "role: Role", workspace_members.created_at
+        FROM workspace_members
+        JOIN users ON users.id = workspace_members.user_id
+        WHERE workspace_members.workspace_id = $1
+        ORDER BY workspace_members.role DESC, users.username;
+        "#,
+        workspace_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
+
+pub async fn get_user_id(
+    db: &mut PgConnection,
+    username: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT id FROM users WHERE username = $1;", username)
+        .fetch_optional(&mut *db)
+        .await
+}
+
+pub async fn insert_member(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    role: Role,
+) -> Result<Option<Member>, sqlx::Error> {
+    sqlx::query_as!(
+        Member,
+        r#"
+        WITH member AS (
+            INSERT INTO workspace_members (workspace_id, user_id, role)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (workspace_id, user_id) DO NOTHING
+            RETURNING *
+        )
+        SELECT
+            member.user_id, users.username,
+            member.role AS "role: Role", member.created_at
+        FROM member
+        JOIN users ON users.id = member.user_id;
+        "#,
+        workspace_id,
+        user_id,
+        role as Role,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn update_member_role(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    role: Role,
+) -> Result<Option<Member>, sqlx::Error> {
+    sqlx::query_as!(
+        Member,
+        r#"
+        UPDATE workspace_members SET
+            role = $3
+        FROM users
+        WHERE
+            workspace_members.workspace_id = $1
+            AND workspace_members.user_id = $2
+            AND users.id = workspace_members.user_id
+        RETURNING
+            workspace_members.user_id, users.username,
+            workspace_members.role AS "role: Role", workspace_members.created_at;
+        "#,
+        workspace_id,
+        user_id,
+        role as Role,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+pub async fn delete_member(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Member>, sqlx::Error> {
+    sqlx::query_as!(
+        Member,
+        r#"
+        DELETE FROM workspace_members
+        USING users
+        WHERE
+            workspace_members.workspace_id = $1
+            AND workspace_members.user_id = $2
+            AND users.id = workspace_members.user_id
+        RETURNING
+            workspace_members.user_id, users.username,
+            workspace_members.role AS "role: Role", workspace_members.created_at;
+        "#,
+        workspace_id,
+        user_id,
+    )
+    .fetch_optional(&mut *db)
+    .await
+}
+
+/// The owners of the workspace, whose rows are locked for the rest of the
+/// transaction.
+pub async fn lock_owners(
+    db: &mut PgConnection,
+    workspace_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT user_id
+        FROM workspace_members
+        WHERE workspace_id = $1 AND role = 'owner'
+        FOR UPDATE;
+        "#,
+        workspace_id,
+    )
+    .fetch_all(&mut *db)
+    .await
+}
@@ -0,0 +1,3 @@
+pub fn is_valid_workspace_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.len() <= 64
+}
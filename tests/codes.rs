@@ -0,0 +1,49 @@
+mod common;
+
+use rocket::{http::Status, serde::json::json};
+
+#[rocket::async_test]
+async fn random_codes_give_up_after_the_attempts_allowed() {
+    let client = common::client_with(|figment| {
+        figment
+            .merge(("short_url_strategy", "random"))
+            .merge(("short_url_length", 1))
+            .merge(("short_url_alphabet", "xy"))
+            .merge(("short_url_max_attempts", 3))
+    })
+    .await;
+    let user = common::user(&client).await;
+
+    let mut statuses = Vec::new();
+    for _ in 0..20 {
+        let response = common::try_create_url(&client, &user, json!({})).await;
+        statuses.push(response.status());
+    }
+
+    assert!(statuses
+        .iter()
+        .all(|s| *s == Status::Ok || *s == Status::Conflict));
+    assert!(statuses.iter().filter(|s| **s == Status::Ok).count() <= 2);
+    assert!(statuses.contains(&Status::Conflict));
+}
+
+#[rocket::async_test]
+async fn sequence_codes_run_out_cleanly() {
+    let client = common::client_with(|figment| {
+        figment
+            .merge(("short_url_strategy", "sequence"))
+            .merge(("short_url_length", 1))
+            .merge(("short_url_alphabet", "pq"))
+    })
+    .await;
+    let user = common::user(&client).await;
+
+    let mut statuses = Vec::new();
+    for _ in 0..4 {
+        let response = common::try_create_url(&client, &user, json!({})).await;
+        statuses.push(response.status());
+    }
+
+    assert!(!statuses.contains(&Status::InternalServerError));
+    assert_eq!(statuses.last(), Some(&Status::ServiceUnavailable));
+}
@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+
+use rocket::{
+    figment::{
+        providers::{Format, Toml},
+        Figment,
+    },
+    http::{ContentType, Header, Status},
+    local::asynchronous::{Client, LocalResponse},
+    serde::json::{json, Value},
+};
+use urlessen::utils::compute_random_32_bytes_key;
+
+const PASSWORD: &str = "passw0rd!long";
+
+/// A client of the application configured as in `App.example.toml`, against
+/// the database in `DATABASE_URL`, which must have its migrations applied.
+pub async fn client() -> Client {
+    client_with(|figment| figment).await
+}
+
+/// Same as [`client`], with some settings overridden.
+pub async fn client_with(settings: impl FnOnce(Figment) -> Figment) -> Client {
+    Client::tracked(urlessen::build(settings(figment())))
+        .await
+        .expect("valid rocket instance")
+}
+
+pub fn figment() -> Figment {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+    rocket::Config::figment()
+        .merge(Toml::file(concat!(env!("CARGO_MANIFEST_DIR"), "/App.example.toml")).nested())
+        .merge(("databases.urlessen.url", database_url))
+        .merge(("log_level", "off"))
+}
+
+pub struct User {
+    pub username: String,
+    pub token: String,
+}
+
+impl User {
+    pub fn auth(&self) -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {}", self.token))
+    }
+}
+
+/// Signs up and in as a new user, named with letters only as usernames must.
+pub async fn user(client: &Client) -> User {
+    let username = compute_random_32_bytes_key()[..16]
+        .chars()
+        .map(|c| (b'a' + c.to_digit(16).unwrap() as u8) as char)
+        .collect::<String>();
+
+    let response = client
+        .post("/auth/signup")
+        .json(&json!({
+            "username": username,
+            "password": PASSWORD,
+            "passwordCheck": PASSWORD,
+        }))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/auth/signin")
+        .json(&json!({ "username": username, "password": PASSWORD }))
+        .dispatch()
+        .await;
+    let token = json_of(response).await["token"]
+        .as_str()
+        .expect("access token")
+        .to_string();
+
+    User { username, token }
+}
+
+/// Creates a link with a generated code, returning the response as is.
+pub async fn try_create_url<'c>(client: &'c Client, user: &User, body: Value) -> LocalResponse<'c> {
+    let mut body = body;
+    let defaults = json!({
+        "title": "A link",
+        "description": "Created by a test",
+        "longUrl": "https://example.com/",
+    });
+
+    for (key, value) in defaults.as_object().unwrap() {
+        body.as_object_mut()
+            .unwrap()
+            .entry(key)
+            .or_insert(value.clone());
+    }
+
+    client
+        .post("/urls")
+        .header(user.auth())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+        .await
+}
+
+pub async fn create_url(client: &Client, user: &User, body: Value) -> Value {
+    let response = try_create_url(client, user, body).await;
+    assert_eq!(response.status(), Status::Ok);
+    json_of(response).await
+}
+
+pub async fn json_of(response: LocalResponse<'_>) -> Value {
+    response.into_json::<Value>().await.expect("JSON body")
+}
+
+pub async fn create_workspace(client: &Client, owner: &User) -> String {
+    let response = client
+        .post("/workspaces")
+        .header(owner.auth())
+        .json(&json!({ "name": "A workspace" }))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    json_of(response).await["id"].as_str().unwrap().to_string()
+}
+
+/// Adds a member to a workspace, returning its user ID.
+pub async fn add_member(
+    client: &Client,
+    owner: &User,
+    workspace: &str,
+    member: &User,
+    role: &str,
+) -> String {
+    let response = client
+        .post(format!("/workspaces/{workspace}/members"))
+        .header(owner.auth())
+        .json(&json!({ "username": member.username, "role": role }))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    json_of(response).await["userId"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// The IDs of the links the user reaches through listing their own links,
+/// searching for `q` and exporting, in that order.
+pub async fn reachable(client: &Client, user: &User, q: &str) -> [Vec<String>; 3] {
+    let ids = |urls: &Value| -> Vec<String> {
+        urls.as_array()
+            .unwrap()
+            .iter()
+            .map(|url| url["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let get = |uri: String| async move {
+        let response = client.get(uri).header(user.auth()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        json_of(response).await
+    };
+
+    let listed = get(format!("/users/{}/urls", user.username)).await;
+    let found = get(format!("/urls/search?q={q}")).await;
+    let exported = get(format!("/users/{}/urls/export?format=json", user.username)).await;
+
+    [ids(&listed["urls"]), ids(&found), ids(&exported)]
+}
+
+pub async fn stats_clicks(client: &Client, user: &User) -> i64 {
+    let response = client
+        .get(format!("/users/{}/stats", user.username))
+        .header(user.auth())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    json_of(response).await["clicks"].as_i64().unwrap()
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use rocket::{error::ErrorKind, local::asynchronous::Client};
+use urlessen::build;
+
+/// Whether the application fails to ignite because of the setting being zero.
+async fn rejects_zero(setting: &str) -> bool {
+    let figment = common::figment().merge((setting, 0));
+
+    match Client::tracked(build(figment)).await {
+        Ok(_) => false,
+        Err(e) => match e.kind() {
+            ErrorKind::FailedFairings(failed) => failed.iter().any(|f| f.name == "Config Check"),
+            _ => false,
+        },
+    }
+}
+
+#[rocket::async_test]
+async fn zero_intervals_are_rejected() {
+    assert!(rejects_zero("expiration_interval_sec").await);
+    assert!(rejects_zero("purge_interval_sec").await);
+}
+
+#[rocket::async_test]
+async fn zero_live_events_capacity_is_rejected() {
+    assert!(rejects_zero("live_events_capacity").await);
+}
+
+#[rocket::async_test]
+async fn zero_live_replay_size_is_rejected() {
+    assert!(rejects_zero("live_replay_size").await);
+}
+
+#[rocket::async_test]
+async fn zero_webhook_settings_are_rejected() {
+    assert!(rejects_zero("webhook_interval_sec").await);
+    assert!(rejects_zero("webhook_batch_size").await);
+    assert!(rejects_zero("webhook_timeout_sec").await);
+    assert!(rejects_zero("webhook_prune_interval_sec").await);
+}
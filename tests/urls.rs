@@ -0,0 +1,195 @@
+mod common;
+
+use rocket::{
+    http::{Accept, ContentType, Status},
+    serde::json::json,
+};
+
+#[rocket::async_test]
+async fn script_urls_are_rejected() {
+    let client = common::client().await;
+    let user = common::user(&client).await;
+
+    for body in [
+        json!({ "fallbackUrl": "javascript:alert(document.domain)" }),
+        json!({ "fallbackUrl": "data:text/html,<script>alert(1)</script>" }),
+        json!({ "longUrl": "javascript:alert(document.domain)" }),
+    ] {
+        let response = common::try_create_url(&client, &user, body).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    let url = common::create_url(
+        &client,
+        &user,
+        json!({ "fallbackUrl": "https://example.com/gone" }),
+    )
+    .await;
+
+    let response = client
+        .patch(format!("/urls/{}", url["id"].as_str().unwrap()))
+        .header(user.auth())
+        .header(ContentType::JSON)
+        .body(json!({ "fallbackUrl": "javascript:alert(1)" }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+/// Whether a browser would send a cookie with the path along with a request
+/// to the other one, as in RFC 6265, which the local client does not check.
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+#[rocket::async_test]
+async fn unlocking_reveals_the_destination_in_the_preview() {
+    let client = common::client().await;
+    let user = common::user(&client).await;
+    let url = common::create_url(
+        &client,
+        &user,
+        json!({
+            "longUrl": "https://example.com/behind/a/password",
+            "password": "open sesame",
+            "visibility": "public",
+        }),
+    )
+    .await;
+    let code = url["shortUrl"].as_str().unwrap();
+    let cookie_name = format!("unlock_{}", url["id"].as_str().unwrap());
+
+    let response = client
+        .get(format!("/{code}+"))
+        .header(Accept::JSON)
+        .dispatch()
+        .await;
+    assert_eq!(common::json_of(response).await["longUrl"], json!(null));
+
+    let response = client
+        .post(format!("/{code}"))
+        .header(ContentType::JSON)
+        .body(json!({ "password": "open sesame" }).to_string())
+        .dispatch()
+        .await;
+    assert!(response.status().class().is_redirection());
+
+    let cookie = response
+        .cookies()
+        .get(&cookie_name)
+        .expect("unlock cookie")
+        .clone();
+    let path = cookie.path().unwrap_or("/");
+    assert!(path_matches(path, &format!("/{code}")));
+    assert!(path_matches(path, &format!("/{code}+")));
+
+    let response = client
+        .get(format!("/{code}+"))
+        .header(Accept::JSON)
+        .cookie(cookie)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        common::json_of(response).await["longUrl"],
+        json!("https://example.com/behind/a/password")
+    );
+}
+
+#[rocket::async_test]
+async fn removed_members_lose_access_to_the_links_they_created() {
+    let client = common::client().await;
+    let owner = common::user(&client).await;
+    let member = common::user(&client).await;
+    let workspace = common::create_workspace(&client, &owner).await;
+    let member_id = common::add_member(&client, &owner, &workspace, &member, "editor").await;
+
+    let q = &member.username;
+    let title = format!("Roadmap {q}");
+    let shared = common::create_url(
+        &client,
+        &member,
+        json!({ "title": title, "workspaceId": workspace }),
+    )
+    .await;
+    let own = common::create_url(&client, &member, json!({ "title": title })).await;
+
+    let code = shared["shortUrl"].as_str().unwrap();
+    let response = client.get(format!("/{code}")).dispatch().await;
+    assert!(response.status().class().is_redirection());
+
+    let shared = shared["id"].as_str().unwrap().to_string();
+    let own = own["id"].as_str().unwrap().to_string();
+
+    for ids in common::reachable(&client, &member, q).await {
+        assert!(ids.contains(&shared) && ids.contains(&own));
+    }
+    assert_eq!(common::stats_clicks(&client, &member).await, 1);
+
+    let response = client
+        .delete(format!("/workspaces/{workspace}/members/{member_id}"))
+        .header(owner.auth())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    for ids in common::reachable(&client, &member, q).await {
+        assert!(!ids.contains(&shared) && ids.contains(&own));
+    }
+    assert_eq!(common::stats_clicks(&client, &member).await, 0);
+}
+
+#[rocket::async_test]
+async fn senders_lose_access_to_the_links_they_transferred() {
+    let client = common::client().await;
+    let sender = common::user(&client).await;
+    let recipient = common::user(&client).await;
+    let workspace = common::create_workspace(&client, &recipient).await;
+
+    let q = &sender.username;
+    let url = common::create_url(
+        &client,
+        &sender,
+        json!({ "title": format!("Handover {q}") }),
+    )
+    .await;
+    let id = url["id"].as_str().unwrap().to_string();
+
+    let code = url["shortUrl"].as_str().unwrap();
+    let response = client.get(format!("/{code}")).dispatch().await;
+    assert!(response.status().class().is_redirection());
+
+    let response = client
+        .post(format!("/urls/{id}/transfer"))
+        .header(sender.auth())
+        .header(ContentType::JSON)
+        .body(json!({ "toWorkspace": workspace }).to_string())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let transfer = common::json_of(response).await;
+
+    let response = client
+        .post(format!(
+            "/urls/transfers/{}/accept",
+            transfer["id"].as_str().unwrap()
+        ))
+        .header(recipient.auth())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    for ids in common::reachable(&client, &sender, q).await {
+        assert!(!ids.contains(&id));
+    }
+    assert_eq!(common::stats_clicks(&client, &sender).await, 0);
+
+    let response = client
+        .get(format!("/urls/{id}"))
+        .header(sender.auth())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NotFound);
+}
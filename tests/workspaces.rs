@@ -0,0 +1,38 @@
+mod common;
+
+use rocket::{http::Status, serde::json::json};
+
+#[rocket::async_test]
+async fn owners_stepping_down_at_once_leave_one_behind() {
+    let client = common::client().await;
+    let first = common::user(&client).await;
+    let second = common::user(&client).await;
+    let workspace = common::create_workspace(&client, &first).await;
+    let second_id = common::add_member(&client, &first, &workspace, &second, "owner").await;
+
+    let response = client
+        .get(format!("/workspaces/{workspace}/members"))
+        .header(first.auth())
+        .dispatch()
+        .await;
+    let members = common::json_of(response).await;
+    let first_id = members
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|member| member["username"] == json!(first.username))
+        .map(|member| member["userId"].as_str().unwrap().to_string())
+        .unwrap();
+
+    let step_down = |user: &common::User, id: &str| {
+        client
+            .delete(format!("/workspaces/{workspace}/members/{id}"))
+            .header(user.auth())
+            .dispatch()
+    };
+    let (a, b) = rocket::tokio::join!(step_down(&first, &first_id), step_down(&second, &second_id));
+
+    let mut statuses = [a.status(), b.status()];
+    statuses.sort_by_key(|status| status.code);
+    assert_eq!(statuses, [Status::Ok, Status::Conflict]);
+}